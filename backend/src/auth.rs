@@ -1,7 +1,22 @@
+use actix_web::{web, HttpRequest, HttpResponse};
+use log::error;
 use oauth2::basic::BasicClient;
 use oauth2::{AuthUrl, ClientId, ClientSecret, RedirectUrl, TokenUrl};
+use reqwest::Client;
+use serde_json::Value;
+use std::collections::HashMap;
 use std::env;
 
+use crate::store::Store;
+use crate::youtube_api::api_url;
+
+pub const READ_SCOPE: &str = "https://www.googleapis.com/auth/youtube.readonly";
+pub const WRITE_SCOPE: &str = "https://www.googleapis.com/auth/youtube";
+// Requis par captions.download
+pub const CAPTIONS_SCOPE: &str = "https://www.googleapis.com/auth/youtube.force-ssl";
+
+const TOKENINFO_URL: &str = "https://oauth2.googleapis.com/tokeninfo";
+
 pub fn oauth_client() -> BasicClient {
     let client_id = env::var("GOOGLE_CLIENT_ID").expect("GOOGLE_CLIENT_ID non défini");
     let client_secret = env::var("GOOGLE_CLIENT_SECRET").expect("GOOGLE_CLIENT_SECRET non défini");
@@ -14,4 +29,118 @@ pub fn oauth_client() -> BasicClient {
         Some(TokenUrl::new("https://oauth2.googleapis.com/token".to_string()).unwrap()),
     )
         .set_redirect_uri(RedirectUrl::new(redirect_uri).unwrap())
-}
\ No newline at end of file
+}
+// Récupère l'access_token depuis l'en-tête Authorization
+pub fn access_token(req: &HttpRequest) -> Result<String, HttpResponse> {
+    match req.headers().get("authorization") {
+        Some(auth) => match auth.to_str() {
+            Ok(auth_str) => Ok(auth_str.replace("Bearer ", "")),
+            Err(_) => Err(HttpResponse::Unauthorized().body("Token invalide")),
+        },
+        None => Err(HttpResponse::Unauthorized().body("Aucun token d'accès fourni")),
+    }
+}
+
+// Variante réservée au flux SSE: EventSource ne peut pas envoyer d'en-tête, le token
+// est alors accepté dans le paramètre `access_token`
+pub fn stream_access_token(req: &HttpRequest) -> Result<String, HttpResponse> {
+    if req.headers().contains_key("authorization") {
+        return access_token(req);
+    }
+    web::Query::<HashMap<String, String>>::from_query(req.query_string())
+        .ok()
+        .and_then(|q| q.get("access_token").cloned())
+        .ok_or_else(|| HttpResponse::Unauthorized().body("Aucun token d'accès fourni"))
+}
+
+// Identifie l'utilisateur par l'id de sa chaîne YouTube, mis en cache par access_token
+pub async fn current_user(store: &Store, access_token: &str) -> Result<String, HttpResponse> {
+    if let Some(user_id) = store.session_user(access_token) {
+        return Ok(user_id);
+    }
+
+    let client = Client::new();
+    let url = api_url("channels", [("part", "id"), ("mine", "true")]);
+    let res = match client.get(url).bearer_auth(access_token).send().await {
+        Ok(r) => r,
+        Err(e) => {
+            error!("Erreur reqwest pour /channels?mine=true: {}", e);
+            return Err(HttpResponse::InternalServerError().body(format!("Erreur reqwest: {}", e)));
+        }
+    };
+
+    if !res.status().is_success() {
+        let status = res.status();
+        let error_body = res.text().await.unwrap_or_default();
+        error!("Erreur HTTP {} pour /channels?mine=true: {}", status, error_body);
+        return Err(HttpResponse::Unauthorized().body(format!("Erreur HTTP {}: {}", status, error_body)));
+    }
+
+    let body: Value = match res.json().await {
+        Ok(body) => body,
+        Err(e) => return Err(HttpResponse::InternalServerError().body(format!("Erreur de parsing: {}", e))),
+    };
+
+    let user_id = match body["items"].as_array().and_then(|items| items.first()).and_then(|item| item["id"].as_str()) {
+        Some(id) => id.to_string(),
+        None => return Err(HttpResponse::NotFound().body("Aucune chaîne associée à ce compte")),
+    };
+
+    store.remember_session(access_token, &user_id);
+    Ok(user_id)
+}
+
+pub async fn authenticated_user(req: &HttpRequest, store: &Store) -> Result<String, HttpResponse> {
+    let access_token = access_token(req)?;
+    current_user(store, &access_token).await
+}
+
+// Utilisateur des endpoints publics, s'il a envoyé un token; None sinon ou si le token est refusé
+pub async fn optional_user(req: &HttpRequest, store: &Store) -> Option<String> {
+    if !req.headers().contains_key("authorization") {
+        return None;
+    }
+    authenticated_user(req, store).await.ok()
+}
+
+// Vérifie que le token présenté couvre le scope demandé, accordé à la demande via
+// /login?write=true ou /login?captions=true; un autre token du même utilisateur ne compte pas
+pub async fn require_scope(req: &HttpRequest, store: &Store, scope: &str) -> Result<String, HttpResponse> {
+    let access_token = access_token(req)?;
+    current_user(store, &access_token).await?;
+
+    let scopes = match store.session_scopes(&access_token) {
+        Some(scopes) => scopes,
+        None => {
+            let scopes = token_scopes(&access_token).await?;
+            store.remember_scopes(&access_token, scopes.clone());
+            scopes
+        }
+    };
+    if !scopes.iter().any(|s| s == scope) {
+        let login = if scope == CAPTIONS_SCOPE { "/login?captions=true" } else { "/login?write=true" };
+        return Err(HttpResponse::Forbidden().body(format!("Autorisation supplémentaire requise: se reconnecter via {}", login)));
+    }
+    Ok(access_token)
+}
+
+// Scopes accordés à un access_token, selon l'endpoint tokeninfo de Google
+async fn token_scopes(access_token: &str) -> Result<Vec<String>, HttpResponse> {
+    let res = match Client::new().get(TOKENINFO_URL).query(&[("access_token", access_token)]).send().await {
+        Ok(r) => r,
+        Err(e) => {
+            error!("Erreur reqwest pour tokeninfo: {}", e);
+            return Err(HttpResponse::InternalServerError().body(format!("Erreur reqwest: {}", e)));
+        }
+    };
+    if !res.status().is_success() {
+        error!("Erreur HTTP {} pour tokeninfo", res.status());
+        return Err(HttpResponse::Unauthorized().body("Token invalide ou expiré"));
+    }
+
+    let body: Value = match res.json().await {
+        Ok(body) => body,
+        Err(e) => return Err(HttpResponse::InternalServerError().body(format!("Erreur de parsing: {}", e))),
+    };
+    Ok(body["scope"].as_str().unwrap_or("").split_whitespace().map(|s| s.to_string()).collect())
+}
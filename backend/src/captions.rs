@@ -0,0 +1,218 @@
+use actix_web::http::StatusCode;
+use actix_web::{get, web, HttpRequest, HttpResponse};
+use chrono::Utc;
+use log::{error, info, warn};
+use reqwest::{Client, Url};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::env;
+
+use crate::auth;
+use crate::store::{Store, StoredTranscript};
+use crate::transcript::{self, Segment};
+use crate::video_detail::is_video_id;
+use crate::youtube_api::api_url;
+
+const TIMEDTEXT_URL: &str = "https://www.youtube.com/api/timedtext";
+
+#[derive(Serialize, Debug)]
+pub struct CaptionTrack {
+    pub id: String,
+    pub language: String,
+    pub name: String,
+    // "standard", "asr" (générée automatiquement) ou "forced"
+    pub track_kind: String,
+    pub is_auto_synced: bool,
+    pub is_cc: bool,
+    pub is_draft: bool,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct TranscriptQuery {
+    // Langue des sous-titres publics, "en" par défaut
+    lang: Option<String>,
+    // "asr" pour les sous-titres générés automatiquement
+    kind: Option<String>,
+    // Piste téléchargée via captions.download, réservé au propriétaire de la vidéo;
+    // nécessite une connexion via /login?captions=true
+    track_id: Option<String>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct Transcript {
+    pub video_id: String,
+    pub language: Option<String>,
+    pub segments: Vec<Segment>,
+}
+
+#[get("/video/{video_id}/captions")]
+pub async fn list_captions(path: web::Path<String>) -> HttpResponse {
+    let api_key = match env::var("YOUTUBE_API_KEY") {
+        Ok(key) => key,
+        Err(_) => return HttpResponse::InternalServerError().body("YOUTUBE_API_KEY non défini"),
+    };
+    let video_id = path.into_inner();
+    if !is_video_id(&video_id) {
+        return HttpResponse::BadRequest().body(format!("video_id invalide: {}", video_id));
+    }
+
+    let url = api_url("captions", [("part", "snippet"), ("videoId", video_id.as_str()), ("key", api_key.as_str())]);
+    let body = match fetch_text(Client::new().get(url), "/captions").await {
+        Ok(body) => body,
+        Err(response) => return response,
+    };
+    let body: Value = match serde_json::from_str(&body) {
+        Ok(body) => body,
+        Err(e) => {
+            error!("Erreur de parsing de la réponse /captions: {}", e);
+            return HttpResponse::InternalServerError().body("Réponse de l'API YouTube illisible");
+        }
+    };
+
+    let tracks: Vec<CaptionTrack> = body["items"]
+        .as_array()
+        .map(|items| items.iter().filter_map(track_from_item).collect())
+        .unwrap_or_default();
+
+    HttpResponse::Ok().json(tracks)
+}
+
+#[get("/video/{video_id}/transcript")]
+pub async fn get_transcript(req: HttpRequest, path: web::Path<String>, query: web::Query<TranscriptQuery>, store: web::Data<Store>) -> HttpResponse {
+    let video_id = path.into_inner();
+    if !is_video_id(&video_id) {
+        return HttpResponse::BadRequest().body(format!("video_id invalide: {}", video_id));
+    }
+
+    let client = Client::new();
+    let (request, language) = match &query.track_id {
+        Some(track_id) => {
+            if !track_id.chars().all(|c| c.is_ascii_alphanumeric() || "-_=".contains(c)) {
+                return HttpResponse::BadRequest().body(format!("track_id invalide: {}", track_id));
+            }
+            let access_token = match auth::require_scope(&req, &store, auth::CAPTIONS_SCOPE).await {
+                Ok(token) => token,
+                Err(response) => return response,
+            };
+            let url = api_url(&format!("captions/{}", track_id), [("tfmt", "vtt")]);
+            (client.get(url).bearer_auth(access_token), None)
+        }
+        None => {
+            let lang = query.lang.clone().unwrap_or_else(|| "en".to_string());
+            let url = timedtext_url(&video_id, &lang, query.kind.as_deref() == Some("asr"));
+            (client.get(url), Some(lang))
+        }
+    };
+
+    info!("Récupération de la transcription de {}", video_id);
+    let content = match fetch_text(request, "timedtext").await {
+        Ok(content) => content,
+        Err(response) => return response,
+    };
+    // L'API timedtext répond 200 avec un corps vide quand la piste n'existe pas
+    if content.trim().is_empty() {
+        return HttpResponse::NotFound().body("Aucune transcription disponible pour cette langue");
+    }
+
+    match transcript::parse(&content) {
+        Ok(segments) => {
+            store.save_transcript(
+                &video_id,
+                StoredTranscript {
+                    language: language.clone(),
+                    segments: segments.clone(),
+                    fetched_at: Utc::now(),
+                },
+            );
+            HttpResponse::Ok().json(Transcript {
+                video_id,
+                language,
+                segments,
+            })
+        }
+        Err(e) => {
+            error!("Transcription de {} illisible: {}", video_id, e);
+            HttpResponse::BadGateway().body(format!("Transcription illisible: {}", e))
+        }
+    }
+}
+
+fn timedtext_url(video_id: &str, lang: &str, asr: bool) -> Url {
+    let mut params = vec![("v", video_id), ("lang", lang), ("fmt", "vtt")];
+    if asr {
+        params.push(("kind", "asr"));
+    }
+    Url::parse_with_params(TIMEDTEXT_URL, &params).expect("URL timedtext invalide")
+}
+
+// Première piste publique disponible parmi les langues demandées, manuelle puis automatique.
+// Une transcription vide est renvoyée si aucune n'existe, pour ne pas réessayer indéfiniment
+pub async fn fetch_public_transcript(client: &Client, video_id: &str, languages: &[String]) -> Result<StoredTranscript, String> {
+    for lang in languages {
+        for asr in [false, true] {
+            let res = client
+                .get(timedtext_url(video_id, lang, asr))
+                .send()
+                .await
+                .map_err(|e| format!("Erreur reqwest pour timedtext: {}", e.without_url()))?;
+            if !res.status().is_success() {
+                continue;
+            }
+            let content = res.text().await.map_err(|e| e.to_string())?;
+            if content.trim().is_empty() {
+                continue;
+            }
+
+            match transcript::parse(&content) {
+                Ok(segments) if !segments.is_empty() => {
+                    return Ok(StoredTranscript {
+                        language: Some(lang.clone()),
+                        segments,
+                        fetched_at: Utc::now(),
+                    })
+                }
+                Ok(_) => {}
+                Err(e) => warn!("Transcription {} de {} illisible: {}", lang, video_id, e),
+            }
+        }
+    }
+
+    Ok(StoredTranscript {
+        language: None,
+        segments: Vec::new(),
+        fetched_at: Utc::now(),
+    })
+}
+
+// Corps de la réponse en texte; le statut d'erreur de YouTube est transmis au client
+async fn fetch_text(request: reqwest::RequestBuilder, label: &str) -> Result<String, HttpResponse> {
+    let res = match request.send().await {
+        Ok(res) => res,
+        Err(e) => {
+            error!("Erreur reqwest pour {}: {}", label, e.without_url());
+            return Err(HttpResponse::InternalServerError().body("Erreur de communication avec YouTube"));
+        }
+    };
+
+    let status = res.status();
+    let body = res.text().await.unwrap_or_default();
+    if !status.is_success() {
+        error!("Erreur HTTP {} pour {}: {}", status, label, body);
+        let status = StatusCode::from_u16(status.as_u16()).unwrap_or(StatusCode::BAD_GATEWAY);
+        return Err(HttpResponse::build(status).body(format!("Erreur de YouTube pour {}", label)));
+    }
+    Ok(body)
+}
+
+fn track_from_item(item: &Value) -> Option<CaptionTrack> {
+    let snippet = &item["snippet"];
+    Some(CaptionTrack {
+        id: item["id"].as_str()?.to_string(),
+        language: snippet["language"].as_str().unwrap_or("").to_string(),
+        name: snippet["name"].as_str().unwrap_or("").to_string(),
+        track_kind: snippet["trackKind"].as_str().unwrap_or("standard").to_lowercase(),
+        is_auto_synced: snippet["isAutoSynced"].as_bool().unwrap_or(false),
+        is_cc: snippet["isCC"].as_bool().unwrap_or(false),
+        is_draft: snippet["isDraft"].as_bool().unwrap_or(false),
+    })
+}
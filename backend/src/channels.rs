@@ -0,0 +1,257 @@
+use actix_web::{get, web, HttpRequest, HttpResponse};
+use chrono::{DateTime, Duration, Utc};
+use log::info;
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::env;
+use std::error::Error;
+
+use crate::auth;
+use crate::models;
+use crate::store::Store;
+use crate::videos::{self, VideosQuery};
+use crate::youtube_api::api_url;
+
+#[derive(Deserialize, Debug)]
+pub struct ChannelSearchQuery {
+    // Nombre de candidats, 10 par défaut
+    limit: Option<usize>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct ChannelCandidate {
+    pub channel_id: String,
+    pub title: String,
+    // "@pseudo" de la chaîne, absent pour les anciennes chaînes sans handle
+    pub handle: Option<String>,
+    pub avatar: String,
+    pub description: String,
+    // Absent si la chaîne masque son nombre d'abonnés
+    pub subscriber_count: Option<u64>,
+}
+
+// Durée pendant laquelle les détails d'une chaîne sont servis depuis le stockage
+const DETAIL_TTL_HOURS: i64 = 24;
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ChannelDetail {
+    pub channel_id: String,
+    pub title: String,
+    pub description: String,
+    pub custom_url: Option<String>,
+    pub published_at: Option<String>,
+    pub thumbnail: String,
+    pub banner: Option<String>,
+    pub country: Option<String>,
+    pub subscriber_count: Option<u64>,
+    pub view_count: Option<u64>,
+    pub video_count: Option<u64>,
+    // URLs Wikipédia des thèmes associés par YouTube
+    pub topic_categories: Vec<String>,
+    pub uploads_playlist: Option<String>,
+    pub fetched_at: DateTime<Utc>,
+}
+
+// Liste les chaînes correspondant à la recherche pour laisser l'utilisateur choisir
+#[get("/channels/search/{query}")]
+pub async fn search_channels(path: web::Path<String>, query: web::Query<ChannelSearchQuery>) -> HttpResponse {
+    let api_key = match env::var("YOUTUBE_API_KEY") {
+        Ok(key) => key,
+        Err(_) => return HttpResponse::InternalServerError().body("YOUTUBE_API_KEY non défini"),
+    };
+    let search = path.into_inner();
+    if search.trim().is_empty() {
+        return HttpResponse::BadRequest().body("Recherche vide");
+    }
+    let limit = query.limit.unwrap_or(10).clamp(1, 50);
+
+    match find_channels(&Client::new(), &api_key, &search, limit).await {
+        Ok(candidates) => HttpResponse::Ok().json(candidates),
+        Err(e) => HttpResponse::InternalServerError().body(format!("Erreur: {}", e)),
+    }
+}
+
+// Vidéos récentes d'une chaîne choisie parmi les candidats
+#[get("/channels/{channel_id}/videos")]
+pub async fn channel_videos(req: HttpRequest, path: web::Path<String>, query: web::Query<VideosQuery>, store: web::Data<Store>) -> HttpResponse {
+    let api_key = match env::var("YOUTUBE_API_KEY") {
+        Ok(key) => key,
+        Err(_) => return HttpResponse::InternalServerError().body("YOUTUBE_API_KEY non défini"),
+    };
+    let channel_id = path.into_inner();
+    if !is_channel_id(&channel_id) {
+        return HttpResponse::BadRequest().body(format!("channel_id invalide: {}", channel_id));
+    }
+
+    match videos::get_channel_videos(&store, &Client::new(), &api_key, &channel_id, query.limit()).await {
+        Ok(videos) => {
+            let user_id = auth::optional_user(&req, &store).await;
+            store.remember(user_id.as_deref(), videos.iter().filter_map(|v| models::Video::try_from(v).ok()).collect());
+            HttpResponse::Ok().json(videos)
+        }
+        Err(e) => HttpResponse::InternalServerError().body(format!("Erreur: {}", e)),
+    }
+}
+
+#[get("/channels/{channel_id}")]
+pub async fn channel(path: web::Path<String>, store: web::Data<Store>) -> HttpResponse {
+    let api_key = match env::var("YOUTUBE_API_KEY") {
+        Ok(key) => key,
+        Err(_) => return HttpResponse::InternalServerError().body("YOUTUBE_API_KEY non défini"),
+    };
+    let channel_id = path.into_inner();
+    if !is_channel_id(&channel_id) {
+        return HttpResponse::BadRequest().body(format!("channel_id invalide: {}", channel_id));
+    }
+
+    match channel_detail(&store, &Client::new(), &api_key, &channel_id).await {
+        Ok(Some(detail)) => HttpResponse::Ok().json(detail),
+        Ok(None) => HttpResponse::NotFound().body("Chaîne introuvable"),
+        Err(e) => HttpResponse::InternalServerError().body(format!("Erreur: {}", e)),
+    }
+}
+
+// Détails d'une chaîne, depuis le stockage s'ils ont moins de 24 heures
+pub async fn channel_detail(store: &Store, client: &Client, api_key: &str, channel_id: &str) -> Result<Option<ChannelDetail>, Box<dyn Error>> {
+    let now = Utc::now();
+    let cached = store.read(|data| data.channel_details.get(channel_id).cloned());
+    if let Some(detail) = cached.filter(|d| now - d.fetched_at < Duration::hours(DETAIL_TTL_HOURS)) {
+        return Ok(Some(detail));
+    }
+
+    info!("Récupération des détails de la chaîne {}", channel_id);
+    let url = api_url(
+        "channels",
+        [
+            ("part", "snippet,statistics,brandingSettings,topicDetails,contentDetails"),
+            ("id", channel_id),
+            ("key", api_key),
+        ],
+    );
+    let res: Value = client.get(url).send().await?.error_for_status()?.json().await?;
+    let detail = match res["items"].as_array().and_then(|items| items.first()) {
+        Some(item) => detail_from_item(item, now),
+        None => return Ok(None),
+    };
+
+    store.write(|data| data.channel_details.insert(detail.channel_id.clone(), detail.clone()));
+    Ok(Some(detail))
+}
+
+// Playlist des uploads, qui ne change jamais: le stockage suffit quel que soit son âge
+pub async fn uploads_playlist(store: &Store, client: &Client, api_key: &str, channel_id: &str) -> Result<Option<String>, Box<dyn Error>> {
+    let known = store.read(|data| {
+        data.channel_details
+            .get(channel_id)
+            .and_then(|d| d.uploads_playlist.clone())
+            .or_else(|| data.channels.get(channel_id).and_then(|s| s.uploads_playlist.clone()))
+    });
+    if known.is_some() {
+        return Ok(known);
+    }
+
+    Ok(channel_detail(store, client, api_key, channel_id)
+        .await?
+        .and_then(|detail| detail.uploads_playlist))
+}
+
+fn detail_from_item(item: &Value, fetched_at: DateTime<Utc>) -> ChannelDetail {
+    let snippet = &item["snippet"];
+    let statistics = &item["statistics"];
+    let count = |field: &str| statistics[field].as_str().and_then(|c| c.parse().ok());
+    let text = |value: &Value| value.as_str().map(|s| s.to_string());
+
+    ChannelDetail {
+        channel_id: item["id"].as_str().unwrap_or("").to_string(),
+        title: snippet["title"].as_str().unwrap_or("Chaîne inconnue").to_string(),
+        description: snippet["description"].as_str().unwrap_or("").to_string(),
+        custom_url: text(&snippet["customUrl"]),
+        published_at: text(&snippet["publishedAt"]),
+        thumbnail: snippet["thumbnails"]["high"]["url"]
+            .as_str()
+            .or_else(|| snippet["thumbnails"]["default"]["url"].as_str())
+            .unwrap_or("")
+            .to_string(),
+        banner: text(&item["brandingSettings"]["image"]["bannerExternalUrl"]),
+        country: text(&snippet["country"]).or_else(|| text(&item["brandingSettings"]["channel"]["country"])),
+        subscriber_count: if statistics["hiddenSubscriberCount"].as_bool().unwrap_or(false) {
+            None
+        } else {
+            count("subscriberCount")
+        },
+        view_count: count("viewCount"),
+        video_count: count("videoCount"),
+        topic_categories: item["topicDetails"]["topicCategories"]
+            .as_array()
+            .map(|topics| topics.iter().filter_map(|t| t.as_str().map(|t| t.to_string())).collect())
+            .unwrap_or_default(),
+        uploads_playlist: text(&item["contentDetails"]["relatedPlaylists"]["uploads"]),
+        fetched_at,
+    }
+}
+
+pub fn is_channel_id(value: &str) -> bool {
+    value.len() == 24
+        && value.starts_with("UC")
+        && value.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+async fn find_channels(client: &Client, api_key: &str, query: &str, limit: usize) -> Result<Vec<ChannelCandidate>, Box<dyn Error>> {
+    // Étape 1: ids des chaînes, dans l'ordre de pertinence
+    let max_results = limit.to_string();
+    let search_url = api_url(
+        "search",
+        [("part", "id"), ("type", "channel"), ("maxResults", &max_results), ("q", query), ("key", api_key)],
+    );
+    let search_res: Value = client.get(search_url).send().await?.error_for_status()?.json().await?;
+    let channel_ids: Vec<String> = search_res["items"]
+        .as_array()
+        .map(|items| {
+            items
+                .iter()
+                .filter_map(|item| item["id"]["channelId"].as_str().map(|id| id.to_string()))
+                .collect()
+        })
+        .unwrap_or_default();
+
+    if channel_ids.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    // Étape 2: détails (handle, avatar, abonnés) en un seul appel
+    let ids = channel_ids.join(",");
+    let url = api_url("channels", [("part", "snippet,statistics"), ("id", &ids), ("key", api_key)]);
+    let res: Value = client.get(url).send().await?.error_for_status()?.json().await?;
+    let items = res["items"].as_array().cloned().unwrap_or_default();
+
+    let candidates = channel_ids
+        .iter()
+        .filter_map(|id| items.iter().find(|item| item["id"].as_str() == Some(id)))
+        .map(candidate_from_item)
+        .collect();
+
+    Ok(candidates)
+}
+
+fn candidate_from_item(item: &Value) -> ChannelCandidate {
+    let snippet = &item["snippet"];
+    let hidden = item["statistics"]["hiddenSubscriberCount"].as_bool().unwrap_or(false);
+
+    ChannelCandidate {
+        channel_id: item["id"].as_str().unwrap_or("").to_string(),
+        title: snippet["title"].as_str().unwrap_or("Chaîne inconnue").to_string(),
+        handle: snippet["customUrl"].as_str().map(|h| h.to_string()),
+        avatar: snippet["thumbnails"]["medium"]["url"]
+            .as_str()
+            .or_else(|| snippet["thumbnails"]["default"]["url"].as_str())
+            .unwrap_or("")
+            .to_string(),
+        description: snippet["description"].as_str().unwrap_or("").to_string(),
+        subscriber_count: if hidden {
+            None
+        } else {
+            item["statistics"]["subscriberCount"].as_str().and_then(|c| c.parse().ok())
+        },
+    }
+}
@@ -0,0 +1,288 @@
+use actix_web::{get, web, HttpResponse};
+use futures::stream::{self, StreamExt};
+use log::{error, info};
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::env;
+
+use crate::video_detail::is_video_id;
+use crate::youtube_api::{api_url, send_json};
+
+// Bornes des appels comments.list déclenchés par all_replies
+const MAX_EXPANDED_THREADS: usize = 20;
+const MAX_REPLY_PAGES: usize = 5;
+const REPLY_CONCURRENCY: usize = 4;
+
+#[derive(Deserialize, Debug)]
+pub struct CommentsQuery {
+    // "time" ou "relevance" (par défaut pour l'API)
+    order: Option<String>,
+    page_token: Option<String>,
+    search_terms: Option<String>,
+    max_results: Option<u32>,
+    // Récupère toutes les réponses via comments.list au lieu des 5 incluses par défaut
+    #[serde(default)]
+    all_replies: bool,
+}
+
+#[derive(Serialize, Debug)]
+pub struct Comment {
+    pub id: String,
+    pub author: String,
+    pub author_channel_id: Option<String>,
+    pub author_avatar: Option<String>,
+    pub text: String,
+    pub like_count: u64,
+    pub published_at: String,
+    pub updated_at: Option<String>,
+    pub parent_id: Option<String>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct CommentThread {
+    pub id: String,
+    pub comment: Comment,
+    pub total_reply_count: u64,
+    pub replies: Vec<Comment>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct CommentPage {
+    pub items: Vec<CommentThread>,
+    pub next_page_token: Option<String>,
+    pub total_results: Option<u64>,
+}
+
+#[get("/video/{video_id}/comments")]
+pub async fn video_comments(path: web::Path<String>, query: web::Query<CommentsQuery>) -> HttpResponse {
+    let api_key = match env::var("YOUTUBE_API_KEY") {
+        Ok(key) => key,
+        Err(_) => return HttpResponse::InternalServerError().body("YOUTUBE_API_KEY non défini"),
+    };
+    let video_id = path.into_inner();
+    if !is_video_id(&video_id) {
+        return HttpResponse::BadRequest().body(format!("video_id invalide: {}", video_id));
+    }
+
+    let mut params: Vec<(&str, String)> = vec![
+        ("part", "snippet,replies".to_string()),
+        ("videoId", video_id.clone()),
+        ("textFormat", "plainText".to_string()),
+        ("maxResults", query.max_results.unwrap_or(20).clamp(1, 100).to_string()),
+        ("key", api_key.clone()),
+    ];
+    if let Some(order) = &query.order {
+        if order != "time" && order != "relevance" {
+            return HttpResponse::BadRequest().body(format!("order invalide: {} (valeurs possibles: time, relevance)", order));
+        }
+        params.push(("order", order.clone()));
+    }
+    if let Some(terms) = query.search_terms.as_ref().filter(|t| !t.trim().is_empty()) {
+        params.push(("searchTerms", terms.clone()));
+    }
+    if let Some(token) = &query.page_token {
+        params.push(("pageToken", token.clone()));
+    }
+
+    let client = Client::new();
+    let body = match send_json(client.get(api_url("commentThreads", &params)), "/commentThreads").await {
+        Ok(body) => body,
+        Err(response) => return response,
+    };
+
+    let mut threads: Vec<CommentThread> = body["items"]
+        .as_array()
+        .map(|items| items.iter().filter_map(thread_from_item).collect())
+        .unwrap_or_default();
+
+    if query.all_replies {
+        let incomplete = threads_to_expand(&threads);
+        info!("Récupération des réponses de {} fils de commentaires", incomplete.len());
+
+        let fetched: Vec<(usize, Result<Vec<Comment>, String>)> = stream::iter(incomplete)
+            .map(|(i, id)| {
+                let client = &client;
+                let api_key = &api_key;
+                async move { (i, fetch_replies(client, api_key, &id).await) }
+            })
+            .buffer_unordered(REPLY_CONCURRENCY)
+            .collect()
+            .await;
+        for (i, replies) in fetched {
+            match replies {
+                Ok(replies) => threads[i].replies = replies,
+                Err(e) => error!("Réponses du fil {} non récupérées: {}", threads[i].id, e),
+            }
+        }
+    }
+
+    HttpResponse::Ok().json(CommentPage {
+        items: threads,
+        next_page_token: body["nextPageToken"].as_str().map(|s| s.to_string()),
+        total_results: body["pageInfo"]["totalResults"].as_u64(),
+    })
+}
+
+// Fils dont l'API n'a pas renvoyé toutes les réponses, avec leur position; les fils
+// au-delà de la limite gardent les réponses incluses par défaut
+fn threads_to_expand(threads: &[CommentThread]) -> Vec<(usize, String)> {
+    threads
+        .iter()
+        .enumerate()
+        .filter(|(_, t)| t.total_reply_count as usize > t.replies.len())
+        .take(MAX_EXPANDED_THREADS)
+        .map(|(i, t)| (i, t.id.clone()))
+        .collect()
+}
+
+// Réponses d'un fil, page par page, dans la limite de MAX_REPLY_PAGES pages de 100
+async fn fetch_replies(client: &Client, api_key: &str, parent_id: &str) -> Result<Vec<Comment>, String> {
+    let mut replies: Vec<Comment> = Vec::new();
+    let mut page_token: Option<String> = None;
+
+    for _ in 0..MAX_REPLY_PAGES {
+        let mut params = vec![
+            ("part", "snippet"),
+            ("parentId", parent_id),
+            ("textFormat", "plainText"),
+            ("maxResults", "100"),
+            ("key", api_key),
+        ];
+        if let Some(token) = &page_token {
+            params.push(("pageToken", token.as_str()));
+        }
+
+        let body: Value = client
+            .get(api_url("comments", &params))
+            .send()
+            .await
+            .and_then(|res| res.error_for_status())
+            .map_err(|e| e.without_url().to_string())?
+            .json()
+            .await
+            .map_err(|e| e.to_string())?;
+
+        if let Some(items) = body["items"].as_array() {
+            replies.extend(items.iter().filter_map(comment_from_item));
+        }
+
+        page_token = body["nextPageToken"].as_str().map(|s| s.to_string());
+        if page_token.is_none() {
+            break;
+        }
+    }
+
+    // L'API renvoie les réponses de la plus récente à la plus ancienne
+    replies.sort_by(|a, b| a.published_at.cmp(&b.published_at));
+    Ok(replies)
+}
+
+fn thread_from_item(item: &Value) -> Option<CommentThread> {
+    let comment = comment_from_item(&item["snippet"]["topLevelComment"])?;
+    let mut replies: Vec<Comment> = item["replies"]["comments"]
+        .as_array()
+        .map(|comments| comments.iter().filter_map(comment_from_item).collect())
+        .unwrap_or_default();
+    replies.sort_by(|a, b| a.published_at.cmp(&b.published_at));
+
+    Some(CommentThread {
+        id: item["id"].as_str()?.to_string(),
+        comment,
+        total_reply_count: item["snippet"]["totalReplyCount"].as_u64().unwrap_or(0),
+        replies,
+    })
+}
+
+fn comment_from_item(item: &Value) -> Option<Comment> {
+    let snippet = &item["snippet"];
+    let text = |value: &Value| value.as_str().map(|s| s.to_string());
+
+    Some(Comment {
+        id: item["id"].as_str()?.to_string(),
+        author: snippet["authorDisplayName"].as_str().unwrap_or("Anonyme").to_string(),
+        author_channel_id: text(&snippet["authorChannelId"]["value"]),
+        author_avatar: text(&snippet["authorProfileImageUrl"]),
+        text: snippet["textDisplay"].as_str().unwrap_or("").to_string(),
+        like_count: snippet["likeCount"].as_u64().unwrap_or(0),
+        published_at: snippet["publishedAt"].as_str().unwrap_or("").to_string(),
+        updated_at: text(&snippet["updatedAt"]).filter(|updated| Some(updated.as_str()) != snippet["publishedAt"].as_str()),
+        parent_id: text(&snippet["parentId"]),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn threads() -> Vec<CommentThread> {
+        let body: Value = serde_json::from_str(include_str!("../tests/fixtures/comment_threads.json")).unwrap();
+        body["items"].as_array().unwrap().iter().filter_map(thread_from_item).collect()
+    }
+
+    #[test]
+    fn parses_thread_with_replies() {
+        let threads = threads();
+        let thread = &threads[0];
+        assert_eq!(thread.id, "UgzDE2tasfmrYLyNkGt4AaABAg");
+        assert_eq!(thread.total_reply_count, 7);
+        assert_eq!(thread.comment.author, "@rustacean");
+        assert_eq!(thread.comment.author_channel_id.as_deref(), Some("UCaYhcUwRBNscFNUKTjgPFiA"));
+        assert_eq!(thread.comment.text, "Super vidéo, merci !");
+        assert_eq!(thread.comment.like_count, 42);
+        // Modifié après publication
+        assert_eq!(thread.comment.updated_at.as_deref(), Some("2026-10-18T10:00:00Z"));
+        assert_eq!(thread.comment.parent_id, None);
+
+        // Réponses remises dans l'ordre chronologique
+        let replies: Vec<&str> = thread.replies.iter().map(|r| r.id.as_str()).collect();
+        assert_eq!(replies, vec!["UgzDE2tasfmrYLyNkGt4AaABAg.A1", "UgzDE2tasfmrYLyNkGt4AaABAg.A2"]);
+        assert_eq!(thread.replies[0].parent_id.as_deref(), Some("UgzDE2tasfmrYLyNkGt4AaABAg"));
+        // Date de modification identique à la publication: non renvoyée
+        assert_eq!(thread.replies[0].updated_at, None);
+    }
+
+    #[test]
+    fn parses_thread_without_replies() {
+        let threads = threads();
+        let thread = &threads[1];
+        assert_eq!(thread.id, "UgwQ8cK3kW5fH0nN6zV4AaABAg");
+        assert_eq!(thread.total_reply_count, 0);
+        assert!(thread.replies.is_empty());
+        // Auteur supprimé: ni nom ni chaîne
+        assert_eq!(thread.comment.author, "Anonyme");
+        assert_eq!(thread.comment.author_channel_id, None);
+    }
+
+    #[test]
+    fn skips_items_without_ids() {
+        // Le troisième élément de la fixture n'a pas de commentaire principal
+        assert_eq!(threads().len(), 2);
+        assert!(comment_from_item(&serde_json::json!({ "snippet": { "textDisplay": "sans id" } })).is_none());
+    }
+
+    #[test]
+    fn caps_expanded_threads() {
+        let template = threads();
+        let mut many: Vec<CommentThread> = Vec::new();
+        for i in 0..MAX_EXPANDED_THREADS * 2 + 10 {
+            let source = &template[i % 2];
+            many.push(CommentThread {
+                id: format!("fil-{}", i),
+                comment: comment_from_item(&serde_json::json!({ "id": format!("c-{}", i), "snippet": {} })).unwrap(),
+                total_reply_count: source.total_reply_count,
+                replies: source.replies.iter().map(|r| comment_from_item(&serde_json::json!({ "id": r.id, "snippet": {} })).unwrap()).collect(),
+            });
+        }
+
+        let expanded = threads_to_expand(&many);
+        assert_eq!(expanded.len(), MAX_EXPANDED_THREADS);
+        // Seuls les fils incomplets (7 réponses annoncées, 2 reçues) sont développés, dans l'ordre
+        assert!(expanded.iter().all(|(i, id)| i % 2 == 0 && *id == format!("fil-{}", i)));
+        assert_eq!(expanded[0], (0, "fil-0".to_string()));
+
+        // Un fil complet n'est pas redemandé
+        let complete = threads_to_expand(&template[1..]);
+        assert!(complete.is_empty());
+    }
+}
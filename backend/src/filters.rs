@@ -0,0 +1,355 @@
+use actix_web::{delete, get, post, web, HttpRequest, HttpResponse};
+use log::{error, info};
+use regex::Regex;
+use reqwest::Client;
+use serde::Serialize;
+use std::env;
+
+use crate::auth;
+use crate::models::Video;
+use crate::store::{FilterRule, RuleAction, Store};
+use crate::subscriptions::fetch_videos;
+
+pub struct CompiledRule {
+    rule: FilterRule,
+    title_regex: Option<Regex>,
+    description_keyword: Option<String>,
+}
+
+impl CompiledRule {
+    pub fn compile(rule: &FilterRule) -> Result<CompiledRule, String> {
+        let has_criteria = rule.title_regex.is_some()
+            || rule.description_keyword.is_some()
+            || rule.channel_id.is_some()
+            || rule.min_duration.is_some()
+            || rule.max_duration.is_some();
+        if !has_criteria {
+            return Err("La règle doit contenir au moins un critère".to_string());
+        }
+
+        // Un mot-clé vide correspondrait à toutes les vidéos
+        if rule.description_keyword.as_ref().is_some_and(|k| k.trim().is_empty()) {
+            return Err("description_keyword ne doit pas être vide".to_string());
+        }
+
+        if let (Some(min), Some(max)) = (rule.min_duration, rule.max_duration) {
+            if min > max {
+                return Err("min_duration doit être inférieur ou égal à max_duration".to_string());
+            }
+        }
+
+        let title_regex = match &rule.title_regex {
+            Some(pattern) => Some(Regex::new(pattern).map_err(|e| format!("Regex invalide: {}", e))?),
+            None => None,
+        };
+
+        Ok(CompiledRule {
+            rule: rule.clone(),
+            title_regex,
+            description_keyword: rule.description_keyword.as_ref().map(|k| k.to_lowercase()),
+        })
+    }
+
+    pub fn matches(&self, video: &Video) -> bool {
+        if let Some(regex) = &self.title_regex {
+            if !regex.is_match(&video.title) {
+                return false;
+            }
+        }
+
+        if let Some(keyword) = &self.description_keyword {
+            if !video.description.to_lowercase().contains(keyword) {
+                return false;
+            }
+        }
+
+        if let Some(channel_id) = &self.rule.channel_id {
+            if &video.channel_id != channel_id {
+                return false;
+            }
+        }
+
+        // Une durée inconnue ne correspond jamais à un critère de durée
+        if self.rule.min_duration.is_some() || self.rule.max_duration.is_some() {
+            match video.duration_seconds {
+                Some(duration) => {
+                    if self.rule.min_duration.is_some_and(|min| duration < min)
+                        || self.rule.max_duration.is_some_and(|max| duration > max)
+                    {
+                        return false;
+                    }
+                }
+                None => return false,
+            }
+        }
+
+        true
+    }
+}
+
+pub fn compile_rules(rules: &[FilterRule]) -> Vec<CompiledRule> {
+    rules
+        .iter()
+        .filter_map(|rule| match CompiledRule::compile(rule) {
+            Ok(compiled) => Some(compiled),
+            Err(e) => {
+                error!("Règle de filtrage {} ignorée: {}", rule.id, e);
+                None
+            }
+        })
+        .collect()
+}
+
+// Une vidéo est masquée si une règle "mute" la vise et qu'aucune règle "allow" ne la protège
+pub fn is_hidden(rules: &[CompiledRule], video: &Video) -> bool {
+    let muted = rules.iter().any(|r| r.rule.action == RuleAction::Mute && r.matches(video));
+    muted && !rules.iter().any(|r| r.rule.action == RuleAction::Allow && r.matches(video))
+}
+
+pub fn apply(rules: &[CompiledRule], videos: Vec<Video>) -> Vec<Video> {
+    videos.into_iter().filter(|v| !is_hidden(rules, v)).collect()
+}
+
+pub fn uses_duration(rules: &[FilterRule]) -> bool {
+    rules.iter().any(|r| r.min_duration.is_some() || r.max_duration.is_some())
+}
+
+// Complète la durée des vidéos qui n'en ont pas via videos.list, et met à jour le stockage
+pub async fn enrich_durations(store: &Store, client: &Client, api_key: &str, videos: Vec<Video>) -> Vec<Video> {
+    let missing: Vec<String> = videos
+        .iter()
+        .filter(|v| v.duration_seconds.is_none())
+        .map(|v| v.video_id.clone())
+        .collect();
+
+    if missing.is_empty() {
+        return videos;
+    }
+
+    info!("Récupération de la durée de {} vidéos", missing.len());
+    let fetched = fetch_videos(client, api_key, &missing).await;
+    store.ingest(fetched.clone());
+
+    videos
+        .into_iter()
+        .map(|mut video| {
+            if let Some(found) = fetched.iter().find(|f| f.video_id == video.video_id) {
+                video.duration_seconds = found.duration_seconds;
+            }
+            video
+        })
+        .collect()
+}
+
+#[derive(Serialize, Debug)]
+pub struct RuleTestResult {
+    pub would_hide: Vec<Video>,
+    pub would_show: Vec<Video>,
+}
+
+#[get("/filters")]
+pub async fn list_filters(req: HttpRequest, store: web::Data<Store>) -> HttpResponse {
+    let user_id = match auth::authenticated_user(&req, &store).await {
+        Ok(id) => id,
+        Err(response) => return response,
+    };
+
+    let rules: Vec<FilterRule> = store.read(|data| {
+        data.users
+            .get(&user_id)
+            .map(|user| user.filters.clone())
+            .unwrap_or_default()
+    });
+
+    HttpResponse::Ok().json(rules)
+}
+
+#[post("/filters")]
+pub async fn create_filter(req: HttpRequest, body: web::Json<FilterRule>, store: web::Data<Store>) -> HttpResponse {
+    let user_id = match auth::authenticated_user(&req, &store).await {
+        Ok(id) => id,
+        Err(response) => return response,
+    };
+
+    let mut rule = body.into_inner();
+    if let Err(e) = CompiledRule::compile(&rule) {
+        return HttpResponse::BadRequest().body(e);
+    }
+
+    store.write(|data| {
+        let filters = &mut data.users.entry(user_id).or_default().filters;
+        rule.id = filters.iter().map(|r| r.id).max().unwrap_or(0) + 1;
+        filters.push(rule.clone());
+    });
+
+    HttpResponse::Created().json(rule)
+}
+
+#[delete("/filters/{id}")]
+pub async fn delete_filter(req: HttpRequest, path: web::Path<u64>, store: web::Data<Store>) -> HttpResponse {
+    let user_id = match auth::authenticated_user(&req, &store).await {
+        Ok(id) => id,
+        Err(response) => return response,
+    };
+    let id = path.into_inner();
+
+    let removed = store.write(|data| match data.users.get_mut(&user_id) {
+        Some(user) => {
+            let before = user.filters.len();
+            user.filters.retain(|r| r.id != id);
+            user.filters.len() != before
+        }
+        None => false,
+    });
+
+    if removed {
+        HttpResponse::NoContent().finish()
+    } else {
+        HttpResponse::NotFound().body("Règle inconnue")
+    }
+}
+
+// Compare le flux actuel avec et sans la règle proposée, sans l'enregistrer
+#[post("/filters/test")]
+pub async fn test_filter(req: HttpRequest, body: web::Json<FilterRule>, store: web::Data<Store>) -> HttpResponse {
+    let user_id = match auth::authenticated_user(&req, &store).await {
+        Ok(id) => id,
+        Err(response) => return response,
+    };
+
+    let candidate = body.into_inner();
+    let compiled_candidate = match CompiledRule::compile(&candidate) {
+        Ok(compiled) => compiled,
+        Err(e) => return HttpResponse::BadRequest().body(e),
+    };
+
+    let (rules, mut videos) = store.read(|data| {
+        let rules = data.users.get(&user_id).map(|u| u.filters.clone()).unwrap_or_default();
+        (rules, data.channel_videos(&data.user_channels(&user_id)))
+    });
+
+    if uses_duration(&rules) || uses_duration(std::slice::from_ref(&candidate)) {
+        match env::var("YOUTUBE_API_KEY") {
+            Ok(api_key) => videos = enrich_durations(&store, &Client::new(), &api_key, videos).await,
+            Err(_) => error!("YOUTUBE_API_KEY non défini, durées non récupérées"),
+        }
+    }
+
+    let current = compile_rules(&rules);
+    let mut proposed = compile_rules(&rules);
+    proposed.push(compiled_candidate);
+
+    let mut result = RuleTestResult {
+        would_hide: Vec::new(),
+        would_show: Vec::new(),
+    };
+    for video in videos {
+        match (is_hidden(&current, &video), is_hidden(&proposed, &video)) {
+            (false, true) => result.would_hide.push(video),
+            (true, false) => result.would_show.push(video),
+            _ => {}
+        }
+    }
+    result.would_hide.sort_by_key(|v| std::cmp::Reverse(v.published_at));
+    result.would_show.sort_by_key(|v| std::cmp::Reverse(v.published_at));
+
+    HttpResponse::Ok().json(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+
+    fn rule(action: RuleAction) -> FilterRule {
+        FilterRule {
+            id: 0,
+            action,
+            title_regex: None,
+            description_keyword: None,
+            channel_id: None,
+            min_duration: None,
+            max_duration: None,
+        }
+    }
+
+    fn video(title: &str, description: &str, channel_id: &str, duration_seconds: Option<u64>) -> Video {
+        Video {
+            url: String::new(),
+            video_id: "dQw4w9WgXcQ".to_string(),
+            published_at: Utc::now(),
+            title: title.to_string(),
+            thumbnail: String::new(),
+            channel_title: String::new(),
+            channel_id: channel_id.to_string(),
+            description: description.to_string(),
+            duration_seconds,
+        }
+    }
+
+    fn compiled(rule: FilterRule) -> CompiledRule {
+        CompiledRule::compile(&rule).unwrap()
+    }
+
+    #[test]
+    fn rejects_invalid_rules() {
+        assert!(CompiledRule::compile(&rule(RuleAction::Mute)).is_err());
+        assert!(CompiledRule::compile(&FilterRule { description_keyword: Some("  ".to_string()), ..rule(RuleAction::Mute) }).is_err());
+        assert!(CompiledRule::compile(&FilterRule { title_regex: Some("(".to_string()), ..rule(RuleAction::Mute) }).is_err());
+        assert!(CompiledRule::compile(&FilterRule { min_duration: Some(60), max_duration: Some(30), ..rule(RuleAction::Mute) }).is_err());
+    }
+
+    #[test]
+    fn matches_each_criterion() {
+        let shorts = compiled(FilterRule { title_regex: Some("(?i)#shorts".to_string()), ..rule(RuleAction::Mute) });
+        assert!(shorts.matches(&video("Incroyable #Shorts", "", "UC1", None)));
+        assert!(!shorts.matches(&video("Tutoriel complet", "", "UC1", None)));
+
+        let sponsor = compiled(FilterRule { description_keyword: Some("Sponsorisé".to_string()), ..rule(RuleAction::Mute) });
+        assert!(sponsor.matches(&video("", "Vidéo SPONSORISÉE par X", "UC1", None)));
+        assert!(!sponsor.matches(&video("", "", "UC1", None)));
+
+        let channel = compiled(FilterRule { channel_id: Some("UC1".to_string()), ..rule(RuleAction::Mute) });
+        assert!(channel.matches(&video("", "", "UC1", None)));
+        assert!(!channel.matches(&video("", "", "UC2", None)));
+    }
+
+    #[test]
+    fn matches_duration_bounds_inclusively() {
+        let short = compiled(FilterRule { min_duration: Some(60), max_duration: Some(120), ..rule(RuleAction::Mute) });
+        assert!(short.matches(&video("", "", "UC1", Some(60))));
+        assert!(short.matches(&video("", "", "UC1", Some(120))));
+        assert!(!short.matches(&video("", "", "UC1", Some(59))));
+        assert!(!short.matches(&video("", "", "UC1", Some(121))));
+        // Une durée inconnue ne correspond pas
+        assert!(!short.matches(&video("", "", "UC1", None)));
+    }
+
+    #[test]
+    fn requires_all_criteria() {
+        let both = compiled(FilterRule {
+            title_regex: Some("live".to_string()),
+            channel_id: Some("UC1".to_string()),
+            ..rule(RuleAction::Mute)
+        });
+        assert!(both.matches(&video("live du soir", "", "UC1", None)));
+        assert!(!both.matches(&video("live du soir", "", "UC2", None)));
+        assert!(!both.matches(&video("replay", "", "UC1", None)));
+    }
+
+    #[test]
+    fn allow_rules_take_precedence_over_mute_rules() {
+        let rules = vec![
+            compiled(FilterRule { channel_id: Some("UC1".to_string()), ..rule(RuleAction::Mute) }),
+            compiled(FilterRule { title_regex: Some("Rust".to_string()), ..rule(RuleAction::Allow) }),
+        ];
+
+        assert!(is_hidden(&rules, &video("Cuisine", "", "UC1", None)));
+        assert!(!is_hidden(&rules, &video("Rust en 10 minutes", "", "UC1", None)));
+        assert!(!is_hidden(&rules, &video("Cuisine", "", "UC2", None)));
+
+        // Une règle "allow" seule ne masque rien
+        let allow_only = vec![compiled(FilterRule { title_regex: Some("Rust".to_string()), ..rule(RuleAction::Allow) })];
+        assert!(!is_hidden(&allow_only, &video("Cuisine", "", "UC1", None)));
+    }
+}
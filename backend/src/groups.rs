@@ -0,0 +1,250 @@
+use actix_web::{delete, get, post, put, web, HttpRequest, HttpResponse};
+use serde::Deserialize;
+
+use crate::auth;
+use crate::channels::is_channel_id;
+use crate::store::{ChannelGroup, Store};
+
+#[derive(Deserialize, Debug)]
+pub struct UpdateGroupRequest {
+    // Nouveau nom, le nom actuel est conservé s'il est absent
+    name: Option<String>,
+    channel_ids: Vec<String>,
+}
+
+#[get("/groups")]
+pub async fn list_groups(req: HttpRequest, store: web::Data<Store>) -> HttpResponse {
+    let user_id = match auth::authenticated_user(&req, &store).await {
+        Ok(id) => id,
+        Err(response) => return response,
+    };
+
+    let groups: Vec<ChannelGroup> = store.read(|data| {
+        data.users
+            .get(&user_id)
+            .map(|user| user.groups.clone())
+            .unwrap_or_default()
+    });
+
+    HttpResponse::Ok().json(groups)
+}
+
+#[post("/groups")]
+pub async fn create_group(req: HttpRequest, body: web::Json<ChannelGroup>, store: web::Data<Store>) -> HttpResponse {
+    let user_id = match auth::authenticated_user(&req, &store).await {
+        Ok(id) => id,
+        Err(response) => return response,
+    };
+
+    let group = body.into_inner();
+    match store.write(|data| create(&mut data.users.entry(user_id).or_default().groups, group)) {
+        Ok(group) => HttpResponse::Created().json(group),
+        Err(response) => response,
+    }
+}
+
+#[put("/groups/{name}")]
+pub async fn update_group(req: HttpRequest, path: web::Path<String>, body: web::Json<UpdateGroupRequest>, store: web::Data<Store>) -> HttpResponse {
+    let user_id = match auth::authenticated_user(&req, &store).await {
+        Ok(id) => id,
+        Err(response) => return response,
+    };
+    let name = path.into_inner();
+    let body = body.into_inner();
+
+    match store.write(|data| update(&mut data.users.entry(user_id).or_default().groups, &name, body)) {
+        Ok(group) => HttpResponse::Ok().json(group),
+        Err(response) => response,
+    }
+}
+
+#[delete("/groups/{name}")]
+pub async fn delete_group(req: HttpRequest, path: web::Path<String>, store: web::Data<Store>) -> HttpResponse {
+    let user_id = match auth::authenticated_user(&req, &store).await {
+        Ok(id) => id,
+        Err(response) => return response,
+    };
+    let name = path.into_inner();
+
+    let removed = store.write(|data| match data.users.get_mut(&user_id) {
+        Some(user) => {
+            let before = user.groups.len();
+            user.groups.retain(|g| g.name != name);
+            user.groups.len() != before
+        }
+        None => false,
+    });
+
+    if removed {
+        HttpResponse::NoContent().finish()
+    } else {
+        HttpResponse::NotFound().body("Groupe inconnu")
+    }
+}
+
+#[post("/groups/{name}/channels/{channel_id}")]
+pub async fn add_channel(req: HttpRequest, path: web::Path<(String, String)>, store: web::Data<Store>) -> HttpResponse {
+    let user_id = match auth::authenticated_user(&req, &store).await {
+        Ok(id) => id,
+        Err(response) => return response,
+    };
+    let (name, channel_id) = path.into_inner();
+    if !is_channel_id(&channel_id) {
+        return HttpResponse::BadRequest().body(format!("channel_id invalide: {}", channel_id));
+    }
+
+    let group = store.write(|data| {
+        let group = data.users.get_mut(&user_id)?.groups.iter_mut().find(|g| g.name == name)?;
+        if !group.channel_ids.contains(&channel_id) {
+            group.channel_ids.push(channel_id);
+        }
+        Some(group.clone())
+    });
+
+    match group {
+        Some(group) => HttpResponse::Ok().json(group),
+        None => HttpResponse::NotFound().body("Groupe inconnu"),
+    }
+}
+
+#[delete("/groups/{name}/channels/{channel_id}")]
+pub async fn remove_channel(req: HttpRequest, path: web::Path<(String, String)>, store: web::Data<Store>) -> HttpResponse {
+    let user_id = match auth::authenticated_user(&req, &store).await {
+        Ok(id) => id,
+        Err(response) => return response,
+    };
+    let (name, channel_id) = path.into_inner();
+
+    let group = store.write(|data| {
+        let group = data.users.get_mut(&user_id)?.groups.iter_mut().find(|g| g.name == name)?;
+        group.channel_ids.retain(|id| id != &channel_id);
+        Some(group.clone())
+    });
+
+    match group {
+        Some(group) => HttpResponse::Ok().json(group),
+        None => HttpResponse::NotFound().body("Groupe inconnu"),
+    }
+}
+
+fn create(groups: &mut Vec<ChannelGroup>, mut group: ChannelGroup) -> Result<ChannelGroup, HttpResponse> {
+    group.name = group.name.trim().to_string();
+    if group.name.is_empty() {
+        return Err(HttpResponse::BadRequest().body("Le nom du groupe est obligatoire"));
+    }
+    normalize_channel_ids(&mut group.channel_ids)?;
+
+    if groups.iter().any(|g| g.name == group.name) {
+        return Err(HttpResponse::Conflict().body("Un groupe porte déjà ce nom"));
+    }
+    groups.push(group.clone());
+    Ok(group)
+}
+
+fn update(groups: &mut [ChannelGroup], name: &str, body: UpdateGroupRequest) -> Result<ChannelGroup, HttpResponse> {
+    let new_name = body.name.map(|n| n.trim().to_string()).unwrap_or_else(|| name.to_string());
+    if new_name.is_empty() {
+        return Err(HttpResponse::BadRequest().body("Le nom du groupe est obligatoire"));
+    }
+    let mut channel_ids = body.channel_ids;
+    normalize_channel_ids(&mut channel_ids)?;
+
+    if new_name != name && groups.iter().any(|g| g.name == new_name) {
+        return Err(HttpResponse::Conflict().body("Un groupe porte déjà ce nom"));
+    }
+    match groups.iter_mut().find(|g| g.name == name) {
+        Some(group) => {
+            group.name = new_name;
+            group.channel_ids = channel_ids;
+            Ok(group.clone())
+        }
+        None => Err(HttpResponse::NotFound().body("Groupe inconnu")),
+    }
+}
+
+// Un id mal formé ne correspondrait jamais à une chaîne du flux
+fn normalize_channel_ids(channel_ids: &mut Vec<String>) -> Result<(), HttpResponse> {
+    if let Some(invalid) = channel_ids.iter().find(|id| !is_channel_id(id)) {
+        return Err(HttpResponse::BadRequest().body(format!("channel_id invalide: {}", invalid)));
+    }
+    channel_ids.sort();
+    channel_ids.dedup();
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::http::StatusCode;
+
+    const RUST: &str = "UCaYhcUwRBNscFNUKTjgPFiA";
+    const MUSIC: &str = "UC-9-kyTW8ZkZNDHQJ6FgpwQ";
+
+    fn group(name: &str, channel_ids: &[&str]) -> ChannelGroup {
+        ChannelGroup {
+            name: name.to_string(),
+            channel_ids: channel_ids.iter().map(|id| id.to_string()).collect(),
+        }
+    }
+
+    fn request(name: Option<&str>, channel_ids: &[&str]) -> UpdateGroupRequest {
+        UpdateGroupRequest {
+            name: name.map(str::to_string),
+            channel_ids: channel_ids.iter().map(|id| id.to_string()).collect(),
+        }
+    }
+
+    fn status(result: Result<ChannelGroup, HttpResponse>) -> StatusCode {
+        match result {
+            Ok(_) => StatusCode::OK,
+            Err(response) => response.status(),
+        }
+    }
+
+    #[test]
+    fn creates_group_with_trimmed_name_and_unique_channels() {
+        let mut groups = Vec::new();
+        let created = create(&mut groups, group("  Rust ", &[RUST, MUSIC, RUST])).unwrap();
+
+        assert_eq!(created.name, "Rust");
+        assert_eq!(created.channel_ids, vec![MUSIC, RUST]);
+        assert_eq!(groups.len(), 1);
+    }
+
+    #[test]
+    fn rejects_invalid_groups_on_create() {
+        let mut groups = vec![group("Rust", &[RUST])];
+
+        assert_eq!(status(create(&mut groups, group("Rust", &[]))), StatusCode::CONFLICT);
+        assert_eq!(status(create(&mut groups, group("   ", &[RUST]))), StatusCode::BAD_REQUEST);
+        assert_eq!(status(create(&mut groups, group("Musique", &[MUSIC, "inconnue"]))), StatusCode::BAD_REQUEST);
+        assert_eq!(groups.len(), 1);
+    }
+
+    #[test]
+    fn renames_and_updates_group() {
+        let mut groups = vec![group("Rust", &[RUST])];
+        let updated = update(&mut groups, "Rust", request(Some("Langages"), &[MUSIC, RUST])).unwrap();
+
+        assert_eq!(updated.name, "Langages");
+        assert_eq!(updated.channel_ids, vec![MUSIC, RUST]);
+        assert_eq!(groups[0].name, "Langages");
+
+        // Sans nom, le nom actuel est conservé
+        let updated = update(&mut groups, "Langages", request(None, &[])).unwrap();
+        assert_eq!(updated.name, "Langages");
+        assert!(updated.channel_ids.is_empty());
+    }
+
+    #[test]
+    fn rejects_invalid_updates() {
+        let mut groups = vec![group("Rust", &[RUST]), group("Musique", &[MUSIC])];
+
+        assert_eq!(status(update(&mut groups, "Rust", request(Some("Musique"), &[RUST]))), StatusCode::CONFLICT);
+        assert_eq!(status(update(&mut groups, "Rust", request(Some(" "), &[RUST]))), StatusCode::BAD_REQUEST);
+        assert_eq!(status(update(&mut groups, "Rust", request(None, &["UCcourt"]))), StatusCode::BAD_REQUEST);
+        assert_eq!(status(update(&mut groups, "Jeux", request(None, &[RUST]))), StatusCode::NOT_FOUND);
+        assert_eq!(groups[0].name, "Rust");
+        assert_eq!(groups[0].channel_ids, vec![RUST]);
+    }
+}
@@ -0,0 +1,432 @@
+use actix_web::{get, web, HttpRequest, HttpResponse};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+
+use crate::auth;
+use crate::models::Video;
+use crate::store::{Store, StoreData};
+use crate::transcript::Segment;
+
+// Poids des champs dans le classement
+const TITLE_WEIGHT: f64 = 3.0;
+const CHANNEL_WEIGHT: f64 = 2.0;
+const DESCRIPTION_WEIGHT: f64 = 1.0;
+// Faible pour qu'une longue transcription ne noie pas les correspondances du titre
+const TRANSCRIPT_WEIGHT: f64 = 0.2;
+// Nombre maximal de passages de transcription renvoyés par vidéo
+const MAX_TRANSCRIPT_HITS: usize = 5;
+
+// Paramètres BM25 usuels
+const K1: f64 = 1.2;
+const B: f64 = 0.75;
+
+// Index inversé en mémoire sur les titres, descriptions, noms de chaîne et transcriptions
+#[derive(Default)]
+pub struct SearchIndex {
+    // terme -> video_id -> fréquence pondérée par champ
+    postings: HashMap<String, HashMap<String, f64>>,
+    // video_id -> (longueur pondérée, termes indexés)
+    documents: HashMap<String, (f64, Vec<String>)>,
+    // Fréquences des métadonnées et de la transcription, gardées à part pour
+    // pouvoir mettre à jour l'une sans perdre l'autre
+    metadata: HashMap<String, HashMap<String, f64>>,
+    spoken: HashMap<String, HashMap<String, f64>>,
+}
+
+impl SearchIndex {
+    pub fn build(data: &StoreData) -> SearchIndex {
+        let mut index = SearchIndex::default();
+        for entry in data.videos.values() {
+            index.add(&entry.video);
+        }
+        for video in data.library.values() {
+            index.add(video);
+        }
+        for (video_id, transcript) in &data.transcripts {
+            index.set_transcript(video_id, &transcript.segments);
+        }
+        index
+    }
+
+    pub fn add(&mut self, video: &Video) {
+        let mut frequencies: HashMap<String, f64> = HashMap::new();
+        let fields = [
+            (&video.title, TITLE_WEIGHT),
+            (&video.channel_title, CHANNEL_WEIGHT),
+            (&video.description, DESCRIPTION_WEIGHT),
+        ];
+        for (text, weight) in fields {
+            for term in tokenize(text) {
+                *frequencies.entry(term).or_insert(0.0) += weight;
+            }
+        }
+
+        self.metadata.insert(video.video_id.clone(), frequencies);
+        self.reindex(&video.video_id);
+    }
+
+    pub fn set_transcript(&mut self, video_id: &str, segments: &[Segment]) {
+        let mut frequencies: HashMap<String, f64> = HashMap::new();
+        for segment in segments {
+            for term in tokenize(&segment.text) {
+                *frequencies.entry(term).or_insert(0.0) += TRANSCRIPT_WEIGHT;
+            }
+        }
+
+        self.spoken.insert(video_id.to_string(), frequencies);
+        self.reindex(video_id);
+    }
+
+    pub fn remove(&mut self, video_id: &str) {
+        self.metadata.remove(video_id);
+        self.spoken.remove(video_id);
+        self.unindex(video_id);
+    }
+
+    // Recombine métadonnées et transcription en un seul document
+    fn reindex(&mut self, video_id: &str) {
+        self.unindex(video_id);
+
+        let mut frequencies: HashMap<String, f64> = HashMap::new();
+        for fields in [self.metadata.get(video_id), self.spoken.get(video_id)].into_iter().flatten() {
+            for (term, frequency) in fields {
+                *frequencies.entry(term.clone()).or_insert(0.0) += frequency;
+            }
+        }
+        if frequencies.is_empty() {
+            return;
+        }
+
+        let length: f64 = frequencies.values().sum();
+        let terms: Vec<String> = frequencies.keys().cloned().collect();
+        for (term, frequency) in frequencies {
+            self.postings
+                .entry(term)
+                .or_default()
+                .insert(video_id.to_string(), frequency);
+        }
+        self.documents.insert(video_id.to_string(), (length, terms));
+    }
+
+    fn unindex(&mut self, video_id: &str) {
+        if let Some((_, terms)) = self.documents.remove(video_id) {
+            for term in terms {
+                if let Some(posting) = self.postings.get_mut(&term) {
+                    posting.remove(video_id);
+                    if posting.is_empty() {
+                        self.postings.remove(&term);
+                    }
+                }
+            }
+        }
+    }
+
+    // Classement BM25; tous les termes de la requête doivent apparaître
+    pub fn search(&self, query: &str) -> Vec<(String, f64)> {
+        let mut terms = tokenize(query);
+        terms.sort();
+        terms.dedup();
+        if terms.is_empty() || self.documents.is_empty() {
+            return Vec::new();
+        }
+
+        let total_docs = self.documents.len() as f64;
+        let average_length = self.documents.values().map(|(length, _)| length).sum::<f64>() / total_docs;
+
+        let mut scores: HashMap<&String, (f64, usize)> = HashMap::new();
+        for term in &terms {
+            let posting = match self.postings.get(term) {
+                Some(posting) => posting,
+                None => return Vec::new(),
+            };
+
+            let df = posting.len() as f64;
+            let idf = (1.0 + (total_docs - df + 0.5) / (df + 0.5)).ln();
+            for (video_id, frequency) in posting {
+                let length = self.documents.get(video_id).map_or(average_length, |(length, _)| *length);
+                let norm = frequency + K1 * (1.0 - B + B * length / average_length);
+                let score = scores.entry(video_id).or_insert((0.0, 0));
+                score.0 += idf * frequency * (K1 + 1.0) / norm;
+                score.1 += 1;
+            }
+        }
+
+        let mut results: Vec<(String, f64)> = scores
+            .into_iter()
+            .filter(|(_, (_, matched))| *matched == terms.len())
+            .map(|(video_id, (score, _))| (video_id.clone(), score))
+            .collect();
+        results.sort_by(|a, b| b.1.total_cmp(&a.1));
+        results
+    }
+}
+
+// Découpe en mots minuscules sans accents, en ignorant la ponctuation
+pub fn tokenize(text: &str) -> Vec<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(|word| word.chars().flat_map(char::to_lowercase).map(fold_accent).collect())
+        .collect()
+}
+
+fn fold_accent(c: char) -> char {
+    match c {
+        'à' | 'â' | 'ä' | 'á' | 'ã' | 'å' => 'a',
+        'é' | 'è' | 'ê' | 'ë' => 'e',
+        'î' | 'ï' | 'í' | 'ì' => 'i',
+        'ô' | 'ö' | 'ó' | 'ò' | 'õ' => 'o',
+        'ù' | 'û' | 'ü' | 'ú' => 'u',
+        'ç' => 'c',
+        'ñ' => 'n',
+        'ÿ' => 'y',
+        _ => c,
+    }
+}
+
+#[derive(Deserialize, Debug)]
+pub struct LibrarySearchQuery {
+    q: String,
+    limit: Option<usize>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct TranscriptHit {
+    pub start: f64,
+    pub text: String,
+    // Lien vers la vidéo positionné au début du passage
+    pub url: String,
+}
+
+#[derive(Serialize, Debug)]
+pub struct LibraryHit {
+    pub score: f64,
+    pub video: Video,
+    pub transcript_hits: Vec<TranscriptHit>,
+}
+
+// Passages de la transcription contenant au moins un des termes recherchés
+fn transcript_hits(video: &Video, segments: &[Segment], terms: &HashSet<String>) -> Vec<TranscriptHit> {
+    segments
+        .iter()
+        .filter(|segment| tokenize(&segment.text).iter().any(|term| terms.contains(term)))
+        .take(MAX_TRANSCRIPT_HITS)
+        .map(|segment| TranscriptHit {
+            start: segment.start,
+            text: segment.text.clone(),
+            url: format!("{}&t={}s", video.url, segment.start.floor() as u64),
+        })
+        .collect()
+}
+
+// Recherche dans les vidéos déjà connues localement, sans consommer de quota.
+// Limitée aux chaînes suivies par l'appelant
+#[get("/library/search")]
+pub async fn search_library(req: HttpRequest, query: web::Query<LibrarySearchQuery>, store: web::Data<Store>) -> HttpResponse {
+    let user_id = match auth::authenticated_user(&req, &store).await {
+        Ok(id) => id,
+        Err(response) => return response,
+    };
+    if query.q.trim().is_empty() {
+        return HttpResponse::BadRequest().body("Paramètre q manquant");
+    }
+    let limit = query.limit.unwrap_or(50).clamp(1, 200);
+
+    let terms: HashSet<String> = tokenize(&query.q).into_iter().collect();
+    let ranked = store.search(&query.q);
+    let (total, hits) = store.read(|data| {
+        let visible = visible_results(data, &user_id, ranked);
+
+        let hits: Vec<LibraryHit> = visible
+            .iter()
+            .take(limit)
+            .map(|(video, video_id, score)| LibraryHit {
+                score: *score,
+                transcript_hits: data
+                    .transcripts
+                    .get(video_id)
+                    .map(|t| transcript_hits(video, &t.segments, &terms))
+                    .unwrap_or_default(),
+                video: (*video).clone(),
+            })
+            .collect();
+        (visible.len(), hits)
+    });
+
+    HttpResponse::Ok().json(serde_json::json!({
+        "query": query.q,
+        "total": total,
+        "items": hits,
+    }))
+}
+
+// Résultats que l'utilisateur peut voir: vidéos de ses abonnements et vidéos qu'il a
+// lui-même consultées; les ids qui ne correspondent plus à aucune vidéo sont écartés
+fn visible_results<'a>(data: &'a StoreData, user_id: &str, ranked: Vec<(String, f64)>) -> Vec<(&'a Video, String, f64)> {
+    let channel_ids: HashSet<String> = data.user_channels(user_id).into_iter().collect();
+    let library_ids = data.users.get(user_id).map(|user| &user.library_ids);
+    ranked
+        .into_iter()
+        .filter_map(|(video_id, score)| {
+            data.find_video(&video_id)
+                .filter(|video| {
+                    channel_ids.contains(&video.channel_id) || library_ids.is_some_and(|ids| ids.contains(&video_id))
+                })
+                .map(|video| (video, video_id, score))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+
+    fn video(video_id: &str, title: &str, channel_title: &str, description: &str) -> Video {
+        Video {
+            url: format!("https://www.youtube.com/watch?v={}", video_id),
+            video_id: video_id.to_string(),
+            published_at: Utc::now(),
+            title: title.to_string(),
+            thumbnail: String::new(),
+            channel_title: channel_title.to_string(),
+            channel_id: "UCaYhcUwRBNscFNUKTjgPFiA".to_string(),
+            description: description.to_string(),
+            duration_seconds: None,
+        }
+    }
+
+    fn from_channel(video_id: &str, title: &str, channel_id: &str) -> Video {
+        Video {
+            channel_id: channel_id.to_string(),
+            ..video(video_id, title, "Chaîne", "")
+        }
+    }
+
+    fn visible_ids(store: &Store, user_id: &str, query: &str) -> Vec<String> {
+        let ranked = store.search(query);
+        store.read(|data| visible_results(data, user_id, ranked).into_iter().map(|(_, id, _)| id).collect())
+    }
+
+    #[test]
+    fn finds_subscribed_and_own_remembered_videos() {
+        let store = Store::in_memory();
+        store.write(|data| data.users.entry("UC_moi".to_string()).or_default().channel_ids = vec!["UC_suivie".to_string()]);
+        store.ingest(vec![from_channel("flux_suivi", "rust flux", "UC_suivie")]);
+        store.ingest(vec![from_channel("flux_autre", "rust flux", "UC_autre")]);
+        // Chaîne non suivie, mais vidéo consultée par l'utilisateur via /search ou /video
+        store.remember(Some("UC_moi"), vec![from_channel("consultee", "rust recherche", "UC_inconnue")]);
+        store.remember(Some("UC_voisin"), vec![from_channel("voisin", "rust recherche", "UC_inconnue")]);
+        store.remember(None, vec![from_channel("anonyme", "rust recherche", "UC_inconnue")]);
+
+        let mut ids = visible_ids(&store, "UC_moi", "rust");
+        ids.sort();
+        assert_eq!(ids, vec!["consultee", "flux_suivi"]);
+        assert_eq!(visible_ids(&store, "UC_voisin", "rust"), vec!["voisin"]);
+
+        store.remove_videos(&["consultee".to_string()]);
+        assert_eq!(visible_ids(&store, "UC_moi", "rust"), vec!["flux_suivi"]);
+    }
+
+    #[test]
+    fn indexes_merged_video_after_partial_update() {
+        let store = Store::in_memory();
+        store.ingest(vec![video("a", "Titre", "Chaîne", "on parle de tokio")]);
+        // Notification WebSub sans description
+        store.ingest(vec![video("a", "Titre modifié", "Chaîne", "")]);
+        assert_eq!(ids(&store.search("tokio")), vec!["a"]);
+        assert_eq!(ids(&store.search("modifie")), vec!["a"]);
+
+        store.remember(Some("UC_moi"), vec![video("b", "Autre", "Chaîne", "description complète")]);
+        store.remember(Some("UC_moi"), vec![video("b", "Autre", "Chaîne", "")]);
+        assert_eq!(ids(&store.search("complete")), vec!["b"]);
+    }
+
+    fn ids(results: &[(String, f64)]) -> Vec<&str> {
+        results.iter().map(|(id, _)| id.as_str()).collect()
+    }
+
+    fn segment(start: f64, text: &str) -> Segment {
+        Segment {
+            start,
+            duration: 2.0,
+            text: text.to_string(),
+        }
+    }
+
+    #[test]
+    fn links_transcript_hits_to_their_start() {
+        let video = video("dQw4w9WgXcQ", "Titre", "Chaîne", "");
+        let segments = vec![
+            segment(0.0, "Bonjour à tous"),
+            segment(12.7, "Aujourd'hui on parle d'Écrans"),
+            segment(65.2, "rien à voir"),
+            segment(3599.99, "les écrans, encore"),
+        ];
+        let terms: HashSet<String> = tokenize("écran écrans").into_iter().collect();
+
+        let hits = transcript_hits(&video, &segments, &terms);
+        let urls: Vec<&str> = hits.iter().map(|hit| hit.url.as_str()).collect();
+        assert_eq!(
+            urls,
+            vec![
+                "https://www.youtube.com/watch?v=dQw4w9WgXcQ&t=12s",
+                "https://www.youtube.com/watch?v=dQw4w9WgXcQ&t=3599s",
+            ]
+        );
+        assert_eq!(hits[0].start, 12.7);
+        assert_eq!(hits[0].text, "Aujourd'hui on parle d'Écrans");
+    }
+
+    #[test]
+    fn caps_transcript_hits() {
+        let video = video("dQw4w9WgXcQ", "Titre", "Chaîne", "");
+        let segments: Vec<Segment> = (0..20).map(|i| segment(i as f64 * 10.0, "rust partout")).collect();
+        let terms: HashSet<String> = ["rust".to_string()].into_iter().collect();
+
+        assert_eq!(transcript_hits(&video, &segments, &terms).len(), MAX_TRANSCRIPT_HITS);
+        assert!(transcript_hits(&video, &segments, &HashSet::new()).is_empty());
+    }
+
+    #[test]
+    fn tokenizes_and_folds_accents() {
+        assert_eq!(tokenize("Élégant café: crème-brûlée!"), vec!["elegant", "cafe", "creme", "brulee"]);
+        assert_eq!(tokenize("Rust 2024 -- l'ÂGE d'or"), vec!["rust", "2024", "l", "age", "d", "or"]);
+        assert_eq!(tokenize("Niño, Noël, Ça"), vec!["nino", "noel", "ca"]);
+        assert!(tokenize("  ... !? ").is_empty());
+    }
+
+    #[test]
+    fn requires_every_query_term() {
+        let mut index = SearchIndex::default();
+        index.add(&video("a", "Tutoriel Rust async", "Chaîne", ""));
+        index.add(&video("b", "Tutoriel Python", "Chaîne", ""));
+
+        assert_eq!(ids(&index.search("tutoriel rust")), vec!["a"]);
+        assert_eq!(ids(&index.search("rust python")), Vec::<&str>::new());
+        assert_eq!(index.search("").len(), 0);
+        // Accents et casse sont ignorés dans la requête aussi
+        assert_eq!(ids(&index.search("TUTORIÉL Rust")), vec!["a"]);
+    }
+
+    #[test]
+    fn ranks_title_above_channel_above_description() {
+        let mut index = SearchIndex::default();
+        index.add(&video("description", "Autre sujet", "Quelqu'un", "on parle de tokio ici"));
+        index.add(&video("titre", "Découvrir tokio", "Quelqu'un", "autre sujet ici"));
+        index.add(&video("chaine", "Autre sujet", "Tokio FR", "autre sujet ici"));
+
+        assert_eq!(ids(&index.search("tokio")), vec!["titre", "chaine", "description"]);
+    }
+
+    #[test]
+    fn removes_and_updates_documents() {
+        let mut index = SearchIndex::default();
+        index.add(&video("a", "Ancien titre", "Chaîne", ""));
+        index.add(&video("a", "Nouveau titre", "Chaîne", ""));
+        assert!(index.search("ancien").is_empty());
+        assert_eq!(ids(&index.search("nouveau")), vec!["a"]);
+
+        index.remove("a");
+        assert!(index.search("nouveau").is_empty());
+    }
+}
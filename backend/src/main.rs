@@ -1,12 +1,34 @@
-use actix_web::{App, HttpServer, http};
+use actix_web::{web, App, HttpServer, http};
 use actix_cors::Cors;
 use dotenv::dotenv;
+use tokio::sync::watch;
 
 mod auth;
 mod subscriptions;
+mod subscribe;
+mod opml;
 mod videos;
+mod video_detail;
+mod comments;
+mod transcript;
+mod captions;
+mod playlists;
+mod channels;
+mod resolver;
 mod models;
 mod search_video;
+mod search_cache;
+mod scheduler;
+mod store;
+mod stream;
+mod websub;
+mod watched;
+mod queue;
+mod groups;
+mod filters;
+mod mutes;
+mod library;
+mod youtube_api;
 
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
@@ -20,18 +42,72 @@ async fn main() -> std::io::Result<()> {
     println!("  GET  /auth/callback");
     println!("  GET  /subscriptions");
     println!("  GET  /subscriptions/videos");
+    println!("  GET  /subscriptions/videos/stream");
+    println!("  POST /subscriptions/sync");
+    println!("  POST /subscriptions/{{channel_id}}");
+    println!("  DEL  /subscriptions/{{channel_id}}");
+    println!("  GET  /video/{{video_id}}");
+    println!("  GET  /video/{{video_id}}/comments");
+    println!("  GET  /video/{{video_id}}/captions");
+    println!("  GET  /video/{{video_id}}/transcript");
+    println!("  GET  /me/playlists");
+    println!("  POST /playlists");
+    println!("  GET  /playlists/{{playlist_id}}/items");
+    println!("  POST /playlists/{{playlist_id}}/items/{{video_id}}");
+    println!("  PUT  /playlists/{{playlist_id}}/items/{{item_id}}");
+    println!("  DEL  /playlists/{{playlist_id}}/items/{{item_id}}");
+    println!("  POST /playlists/{{playlist_id}}/videos");
+    println!("  GET  /channels/search/{{query}}");
+    println!("  GET  /channels/{{channel_id}}");
+    println!("  GET  /channels/{{channel_id}}/videos");
+    println!("  GET  /websub/callback");
+    println!("  POST /websub/callback");
+    println!("  GET  /watched");
+    println!("  POST /watched");
+    println!("  POST /watched/{{video_id}}");
+    println!("  DEL  /watched/{{video_id}}");
+    println!("  GET  /queue");
+    println!("  PUT  /queue");
+    println!("  POST /queue/{{video_id}}");
+    println!("  DEL  /queue/{{video_id}}");
+    println!("  GET  /groups");
+    println!("  POST /groups");
+    println!("  PUT  /groups/{{name}}");
+    println!("  DEL  /groups/{{name}}");
+    println!("  POST /groups/{{name}}/channels/{{channel_id}}");
+    println!("  DEL  /groups/{{name}}/channels/{{channel_id}}");
+    println!("  GET  /filters");
+    println!("  POST /filters");
+    println!("  POST /filters/test");
+    println!("  DEL  /filters/{{id}}");
+    println!("  GET  /muted");
+    println!("  POST /muted/{{channel_id}}");
+    println!("  DEL  /muted/{{channel_id}}");
+    println!("  GET  /library/search?q=");
 
-    HttpServer::new(|| {
+    let store = web::Data::new(store::Store::load());
+    let search_cache = web::Data::new(search_cache::SearchCache::from_env(&store));
+    tokio::spawn(websub::renew_leases(store.clone()));
+
+    let (shutdown_tx, shutdown_rx) = watch::channel(false);
+    let scheduler = tokio::spawn(scheduler::run(store.clone(), shutdown_rx));
+    tokio::spawn(store::Store::persist_loop(store.clone()));
+    let final_store = store.clone();
+
+    let result = HttpServer::new(move || {
         App::new()
+            .app_data(store.clone())
+            .app_data(search_cache.clone())
             .wrap(
                 Cors::default()
                     .allowed_origin("http://localhost:3000")
-                    .allowed_methods(vec!["GET", "POST"])
+                    .allowed_methods(vec!["GET", "POST", "PUT", "DELETE"])
                     .allowed_headers(vec![
                         http::header::AUTHORIZATION,
                         http::header::CONTENT_TYPE,
                         http::header::HeaderName::from_static("refresh_token"),
                         http::header::HeaderName::from_static("expires_in"),
+                        http::header::HeaderName::from_static("last-event-id"),
                     ])
                     .expose_headers(vec![
                         http::header::AUTHORIZATION,
@@ -43,10 +119,59 @@ async fn main() -> std::io::Result<()> {
             .service(subscriptions::callback)
             .service(subscriptions::subscriptions)
             .service(subscriptions::subscriptions_videos)
+            .service(stream::subscriptions_videos_stream)
+            .service(subscribe::sync_from_opml)
+            .service(subscribe::subscribe)
+            .service(subscribe::unsubscribe)
             .service(videos::videos)
             .service(search_video::search_youtube_videos)
+            .service(video_detail::video)
+            .service(comments::video_comments)
+            .service(captions::list_captions)
+            .service(captions::get_transcript)
+            .service(playlists::my_playlists)
+            .service(playlists::playlist_items)
+            .service(playlists::create_playlist)
+            .service(playlists::add_playlist_item)
+            .service(playlists::move_playlist_item)
+            .service(playlists::remove_playlist_item)
+            .service(playlists::add_playlist_videos)
+            .service(channels::search_channels)
+            .service(channels::channel)
+            .service(channels::channel_videos)
+            .service(websub::verify)
+            .service(websub::notify)
+            .service(watched::list_watched)
+            .service(watched::mark_watched_before)
+            .service(watched::mark_watched)
+            .service(watched::mark_unwatched)
+            .service(queue::get_queue)
+            .service(queue::reorder_queue)
+            .service(queue::add_to_queue)
+            .service(queue::remove_from_queue)
+            .service(groups::list_groups)
+            .service(groups::create_group)
+            .service(groups::update_group)
+            .service(groups::delete_group)
+            .service(groups::add_channel)
+            .service(groups::remove_channel)
+            .service(filters::list_filters)
+            .service(filters::create_filter)
+            .service(filters::test_filter)
+            .service(filters::delete_filter)
+            .service(mutes::list_muted)
+            .service(mutes::mute_channel)
+            .service(mutes::unmute_channel)
+            .service(library::search_library)
     })
         .bind(("0.0.0.0", 8080))?
         .run()
-        .await
+        .await;
+
+    // Le serveur s'est arrêté proprement, on attend la fin du planificateur puis la dernière sauvegarde
+    let _ = shutdown_tx.send(true);
+    let _ = scheduler.await;
+    final_store.flush().await;
+
+    result
 }
\ No newline at end of file
@@ -1,7 +1,11 @@
 use serde::{Deserialize, Serialize};
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, ParseError, Utc};
 
-#[derive(Serialize, Deserialize, Clone, Debug)]
+use crate::search_video;
+use crate::subscriptions::parse_iso8601_duration;
+use crate::video_detail::VideoDetail;
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
 pub struct Video {
     pub url: String,
     pub video_id: String,
@@ -9,12 +13,77 @@ pub struct Video {
     pub title: String,
     pub thumbnail: String,
     pub channel_title: String,
+    #[serde(default)]
+    pub channel_id: String,
+    #[serde(default)]
+    pub description: String,
+    // Absente tant que la vidéo n'a pas été enrichie via videos.list
+    #[serde(default)]
+    pub duration_seconds: Option<u64>,
+}
+
+fn parse_published_at(published_at: &str) -> Result<DateTime<Utc>, ParseError> {
+    DateTime::parse_from_rfc3339(published_at).map(|date| date.with_timezone(&Utc))
+}
+
+// Versions conservées dans la bibliothèque locale pour /library/search
+impl TryFrom<&search_video::Video> for Video {
+    type Error = ParseError;
+
+    fn try_from(video: &search_video::Video) -> Result<Video, ParseError> {
+        Ok(Video {
+            url: format!("https://www.youtube.com/watch?v={}", video.video_id),
+            video_id: video.video_id.clone(),
+            published_at: parse_published_at(&video.published_at)?,
+            title: video.title.clone(),
+            thumbnail: video.thumbnail.clone(),
+            channel_title: video.channel_title.clone(),
+            channel_id: video.channel_id.clone(),
+            description: video.description.clone(),
+            duration_seconds: parse_iso8601_duration(&video.duration),
+        })
+    }
+}
+
+impl TryFrom<&VideoDetail> for Video {
+    type Error = ParseError;
+
+    fn try_from(detail: &VideoDetail) -> Result<Video, ParseError> {
+        Ok(Video {
+            url: detail.url.clone(),
+            video_id: detail.video_id.clone(),
+            published_at: parse_published_at(&detail.published_at)?,
+            title: detail.title.clone(),
+            thumbnail: detail.thumbnail.clone(),
+            channel_title: detail.channel_title.clone(),
+            channel_id: detail.channel_id.clone(),
+            description: detail.description.clone(),
+            duration_seconds: detail.duration_seconds,
+        })
+    }
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct SavedToken {
     pub access_token: String,
     pub refresh_token: Option<String>,
     pub expires_in: Option<u64>,
     pub issued_at: DateTime<Utc>,
-}
\ No newline at end of file
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct Subscription {
+    // Id de l'abonnement, distinct de celui de la chaîne
+    pub subscription_id: String,
+    pub channel_id: String,
+    pub title: String,
+    pub description: String,
+    pub thumbnail: String,
+    pub subscribed_at: Option<DateTime<Utc>>,
+    // Vidéos publiées depuis la dernière visite de la chaîne, selon YouTube
+    pub new_item_count: u64,
+    pub total_item_count: u64,
+    // Dernière vidéo connue localement pour cette chaîne; absente tant que la chaîne
+    // n'a jamais été chargée dans le flux
+    pub last_upload_at: Option<DateTime<Utc>>,
+}
@@ -0,0 +1,202 @@
+use actix_web::{delete, get, post, web, HttpRequest, HttpResponse};
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::auth;
+use crate::store::{ChannelMute, Store};
+
+#[derive(Deserialize, Debug)]
+pub struct MuteQuery {
+    // Date de fin explicite (RFC 3339)
+    until: Option<DateTime<Utc>>,
+    // Ou durée en jours à partir de maintenant
+    days: Option<i64>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct MutedChannel {
+    pub channel_id: String,
+    pub channel_title: Option<String>,
+    pub muted_at: DateTime<Utc>,
+    pub until: Option<DateTime<Utc>>,
+}
+
+// Liste les chaînes en sourdine et purge celles dont la période est terminée
+#[get("/muted")]
+pub async fn list_muted(req: HttpRequest, store: web::Data<Store>) -> HttpResponse {
+    let user_id = match auth::authenticated_user(&req, &store).await {
+        Ok(id) => id,
+        Err(response) => return response,
+    };
+
+    let now = Utc::now();
+    let mutes: Vec<(String, ChannelMute)> = store.write(|data| match data.users.get_mut(&user_id) {
+        Some(user) => {
+            user.muted_channels.retain(|_, mute| mute.is_active(now));
+            user.muted_channels.iter().map(|(id, mute)| (id.clone(), mute.clone())).collect()
+        }
+        None => Vec::new(),
+    });
+
+    let mut muted: Vec<MutedChannel> = store.read(|data| {
+        mutes
+            .into_iter()
+            .map(|(channel_id, mute)| MutedChannel {
+                channel_title: data
+                    .videos
+                    .values()
+                    .find(|v| v.video.channel_id == channel_id)
+                    .map(|v| v.video.channel_title.clone()),
+                channel_id,
+                muted_at: mute.muted_at,
+                until: mute.until,
+            })
+            .collect()
+    });
+    muted.sort_by_key(|m| std::cmp::Reverse(m.muted_at));
+
+    HttpResponse::Ok().json(muted)
+}
+
+#[post("/muted/{channel_id}")]
+pub async fn mute_channel(req: HttpRequest, path: web::Path<String>, query: web::Query<MuteQuery>, store: web::Data<Store>) -> HttpResponse {
+    let user_id = match auth::authenticated_user(&req, &store).await {
+        Ok(id) => id,
+        Err(response) => return response,
+    };
+    let channel_id = path.into_inner();
+
+    let now = Utc::now();
+    let until = match mute_until(&query, now) {
+        Ok(until) => until,
+        Err(response) => return response,
+    };
+
+    let mute = ChannelMute {
+        muted_at: now,
+        until,
+    };
+    store.write(|data| {
+        data.users
+            .entry(user_id)
+            .or_default()
+            .muted_channels
+            .insert(channel_id.clone(), mute.clone());
+    });
+
+    HttpResponse::Ok().json(MutedChannel {
+        channel_id,
+        channel_title: None,
+        muted_at: mute.muted_at,
+        until: mute.until,
+    })
+}
+
+#[delete("/muted/{channel_id}")]
+pub async fn unmute_channel(req: HttpRequest, path: web::Path<String>, store: web::Data<Store>) -> HttpResponse {
+    let user_id = match auth::authenticated_user(&req, &store).await {
+        Ok(id) => id,
+        Err(response) => return response,
+    };
+    let channel_id = path.into_inner();
+
+    let removed = store.write(|data| {
+        data.users
+            .get_mut(&user_id)
+            .and_then(|user| user.muted_channels.remove(&channel_id))
+            .is_some()
+    });
+
+    if removed {
+        HttpResponse::NoContent().finish()
+    } else {
+        HttpResponse::NotFound().body("Chaîne non mise en sourdine")
+    }
+}
+
+// Fin de la mise en sourdine demandée, None pour une sourdine permanente
+fn mute_until(query: &MuteQuery, now: DateTime<Utc>) -> Result<Option<DateTime<Utc>>, HttpResponse> {
+    let until = match (query.until, query.days) {
+        (Some(_), Some(_)) => return Err(HttpResponse::BadRequest().body("until et days sont incompatibles")),
+        (Some(until), None) => Some(until),
+        (None, Some(days)) if days > 0 => Some(now + Duration::days(days)),
+        (None, Some(_)) => return Err(HttpResponse::BadRequest().body("days doit être positif")),
+        (None, None) => None,
+    };
+
+    if until.is_some_and(|until| until <= now) {
+        return Err(HttpResponse::BadRequest().body("until doit être dans le futur"));
+    }
+    Ok(until)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::{StoreData, UserData};
+    use actix_web::http::StatusCode;
+
+    fn query(until: Option<DateTime<Utc>>, days: Option<i64>) -> MuteQuery {
+        MuteQuery { until, days }
+    }
+
+    fn rejected(result: Result<Option<DateTime<Utc>>, HttpResponse>) -> Option<StatusCode> {
+        result.err().map(|response| response.status())
+    }
+
+    #[test]
+    fn computes_mute_end() {
+        let now = Utc::now();
+        let until = now + Duration::hours(3);
+
+        assert_eq!(mute_until(&query(None, None), now).unwrap(), None);
+        assert_eq!(mute_until(&query(Some(until), None), now).unwrap(), Some(until));
+        assert_eq!(mute_until(&query(None, Some(7)), now).unwrap(), Some(now + Duration::days(7)));
+    }
+
+    #[test]
+    fn rejects_invalid_mute_queries() {
+        let now = Utc::now();
+        let cases = [
+            query(Some(now + Duration::days(1)), Some(1)),
+            query(None, Some(0)),
+            query(None, Some(-3)),
+            query(Some(now), None),
+            query(Some(now - Duration::days(1)), None),
+        ];
+        for case in cases {
+            assert_eq!(rejected(mute_until(&case, now)), Some(StatusCode::BAD_REQUEST), "{:?}", case);
+        }
+    }
+
+    #[test]
+    fn only_active_mutes_hide_channels() {
+        let now = Utc::now();
+        let mute = |until: Option<DateTime<Utc>>| ChannelMute {
+            muted_at: now - Duration::days(10),
+            until,
+        };
+        let mut data = StoreData::default();
+        data.users.insert(
+            "UC_user".to_string(),
+            UserData {
+                muted_channels: [
+                    ("UC_expire".to_string(), mute(Some(now - Duration::minutes(1)))),
+                    ("UC_active".to_string(), mute(Some(now + Duration::days(1)))),
+                    ("UC_permanente".to_string(), mute(None)),
+                ]
+                .into(),
+                ..UserData::default()
+            },
+        );
+
+        let mut muted = data.muted_channels("UC_user", now);
+        muted.sort();
+        assert_eq!(muted, vec!["UC_active", "UC_permanente"]);
+
+        // La sourdine active expire à sa date de fin
+        let later = now + Duration::days(2);
+        assert_eq!(data.muted_channels("UC_user", later), vec!["UC_permanente"]);
+        assert!(data.muted_channels("UC_inconnu", now).is_empty());
+    }
+}
@@ -0,0 +1,91 @@
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::Reader;
+use reqwest::Url;
+
+use crate::channels::is_channel_id;
+
+// Chaînes YouTube d'un fichier OPML (export de YouTube ou d'un lecteur RSS), sans doublons.
+// Les flux qui ne désignent pas une chaîne YouTube sont ignorés
+pub fn channel_ids(content: &str) -> Result<Vec<String>, String> {
+    let mut reader = Reader::from_str(content);
+    reader.trim_text(true);
+
+    let mut ids: Vec<String> = Vec::new();
+    loop {
+        match reader.read_event() {
+            Ok(Event::Start(e)) | Ok(Event::Empty(e)) if e.name().as_ref() == b"outline" => {
+                if let Some(id) = outline_channel_id(&e) {
+                    if !ids.contains(&id) {
+                        ids.push(id);
+                    }
+                }
+            }
+            Ok(Event::Eof) => break,
+            Err(e) => return Err(format!("position {}: {}", reader.buffer_position(), e)),
+            _ => {}
+        }
+    }
+
+    Ok(ids)
+}
+
+// xmlUrl "https://www.youtube.com/feeds/videos.xml?channel_id=UC..."
+// ou htmlUrl "https://www.youtube.com/channel/UC..."
+fn outline_channel_id(e: &BytesStart) -> Option<String> {
+    for attr in e.attributes().flatten() {
+        let value = match attr.unescape_value() {
+            Ok(value) => value,
+            Err(_) => continue,
+        };
+        let url = match Url::parse(&value) {
+            Ok(url) => url,
+            Err(_) => continue,
+        };
+        if !url.host_str().is_some_and(|host| host == "youtube.com" || host.ends_with(".youtube.com")) {
+            continue;
+        }
+
+        let id = match attr.key.as_ref() {
+            b"xmlUrl" => url.query_pairs().find(|(k, _)| k == "channel_id").map(|(_, v)| v.into_owned()),
+            b"htmlUrl" => url.path().strip_prefix("/channel/").map(|id| id.trim_end_matches('/').to_string()),
+            _ => None,
+        };
+        if let Some(id) = id.filter(|id| is_channel_id(id)) {
+            return Some(id);
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn extracts_youtube_channels_from_opml_fixture() {
+        let ids = channel_ids(include_str!("../tests/fixtures/subscriptions.opml")).unwrap();
+        assert_eq!(
+            ids,
+            vec![
+                "UCaYhcUwRBNscFNUKTjgPFiA".to_string(),
+                "UC_x5XG1OV2P6uZZ5FSM9Ttw".to_string(),
+                "UCsBjURrPoezykLs9EqgamOA".to_string(),
+            ]
+        );
+    }
+
+    #[test]
+    fn ignores_lookalike_hosts() {
+        let opml = r#"<opml><body>
+            <outline xmlUrl="https://notyoutube.com/feeds/videos.xml?channel_id=UCaYhcUwRBNscFNUKTjgPFiA" />
+            <outline htmlUrl="https://youtube.com.evil.example/channel/UC_x5XG1OV2P6uZZ5FSM9Ttw" />
+            <outline htmlUrl="https://m.youtube.com/channel/UCsBjURrPoezykLs9EqgamOA" />
+        </body></opml>"#;
+        assert_eq!(channel_ids(opml).unwrap(), vec!["UCsBjURrPoezykLs9EqgamOA".to_string()]);
+    }
+
+    #[test]
+    fn rejects_malformed_opml() {
+        assert!(channel_ids("<opml><body><outline xmlUrl=\"x\"></body></opml>").is_err());
+    }
+}
@@ -0,0 +1,444 @@
+use actix_web::{delete, get, post, put, web, HttpRequest, HttpResponse};
+use log::{error, info, warn};
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::env;
+
+use crate::auth;
+use crate::models::Video;
+use crate::store::Store;
+use crate::subscriptions::{fetch_videos, video_from_playlist_item, video_from_videos_item};
+use crate::video_detail::is_video_id;
+use crate::youtube_api::{api_url, send_json};
+
+// Playlist spéciale des vidéos aimées, lue via videos?myRating=like
+const LIKES: &str = "likes";
+// Nombre maximal de vidéos ajoutées par POST /playlists/{playlist_id}/videos
+const MAX_BATCH_VIDEOS: usize = 50;
+
+#[derive(Deserialize, Debug)]
+pub struct PageQuery {
+    page_token: Option<String>,
+    max_results: Option<u32>,
+}
+
+impl PageQuery {
+    fn params(&self) -> Vec<(&'static str, String)> {
+        let mut params = vec![("maxResults", self.max_results.unwrap_or(50).clamp(1, 50).to_string())];
+        if let Some(token) = &self.page_token {
+            params.push(("pageToken", token.clone()));
+        }
+        params
+    }
+}
+
+#[derive(Deserialize, Debug)]
+pub struct CreatePlaylistRequest {
+    title: String,
+    #[serde(default)]
+    description: String,
+    // "private" par défaut, "unlisted" ou "public"
+    privacy_status: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct PositionQuery {
+    // Position dans la playlist, à la fin par défaut
+    position: Option<u32>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct MoveRequest {
+    position: u32,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct AddVideosRequest {
+    video_ids: Vec<String>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct PlaylistEntry {
+    // Id de l'élément, à utiliser pour le déplacer ou le retirer
+    pub item_id: String,
+    pub video_id: String,
+    pub position: Option<u64>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct AddVideosResult {
+    pub added: Vec<PlaylistEntry>,
+    pub failed: Vec<String>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct Playlist {
+    pub id: String,
+    pub title: String,
+    pub description: String,
+    pub thumbnail: String,
+    pub item_count: Option<u64>,
+    pub privacy_status: Option<String>,
+    // "likes" pour la playlist des vidéos aimées
+    pub special: Option<String>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct PlaylistPage {
+    pub items: Vec<Playlist>,
+    pub next_page_token: Option<String>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct PlaylistItemsPage {
+    pub items: Vec<Video>,
+    pub next_page_token: Option<String>,
+    pub total_results: Option<u64>,
+}
+
+// Playlists de l'utilisateur; la première page commence par ses vidéos aimées
+#[get("/me/playlists")]
+pub async fn my_playlists(req: HttpRequest, query: web::Query<PageQuery>) -> HttpResponse {
+    let access_token = match auth::access_token(&req) {
+        Ok(token) => token,
+        Err(response) => return response,
+    };
+    let client = Client::new();
+
+    let mut params = vec![("part", "snippet,contentDetails,status".to_string()), ("mine", "true".to_string())];
+    params.extend(query.params());
+    let body = match send_json(client.get(api_url("playlists", &params)).bearer_auth(&access_token), "/playlists").await {
+        Ok(body) => body,
+        Err(response) => return response,
+    };
+
+    let mut playlists: Vec<Playlist> = Vec::new();
+    if query.page_token.is_none() {
+        match likes_playlist_id(&client, &access_token).await {
+            Some(likes) => playlists.push(Playlist {
+                id: likes,
+                title: "Vidéos J'aime".to_string(),
+                description: String::new(),
+                thumbnail: String::new(),
+                item_count: None,
+                privacy_status: Some("private".to_string()),
+                special: Some(LIKES.to_string()),
+            }),
+            None => error!("Playlist des vidéos aimées introuvable"),
+        }
+    }
+
+    if let Some(items) = body["items"].as_array() {
+        playlists.extend(items.iter().filter_map(playlist_from_item));
+    }
+
+    HttpResponse::Ok().json(PlaylistPage {
+        items: playlists,
+        next_page_token: body["nextPageToken"].as_str().map(|s| s.to_string()),
+    })
+}
+
+// Contenu d'une playlist; les playlists privées et les vidéos aimées nécessitent un token
+#[get("/playlists/{playlist_id}/items")]
+pub async fn playlist_items(req: HttpRequest, path: web::Path<String>, query: web::Query<PageQuery>, store: web::Data<Store>) -> HttpResponse {
+    let playlist_id = path.into_inner();
+    if !is_resource_id(&playlist_id) {
+        return HttpResponse::BadRequest().body(format!("playlist_id invalide: {}", playlist_id));
+    }
+    let access_token = auth::access_token(&req).ok();
+    let api_key = env::var("YOUTUBE_API_KEY").ok();
+    let client = Client::new();
+
+    // Seule la playlist des vidéos aimées de l'appelant passe par videos?myRating=like;
+    // celle d'un autre utilisateur suit le chemin normal et l'API décide de l'accès
+    let mut likes_token: Option<&str> = None;
+    if let Some(token) = access_token.as_deref().filter(|_| playlist_id.starts_with("LL")) {
+        if likes_playlist_id(&client, token).await.as_deref() == Some(playlist_id.as_str()) {
+            likes_token = Some(token);
+        }
+    }
+
+    let page = if let Some(token) = likes_token {
+        liked_videos(&client, token, &query).await
+    } else {
+        let mut params = vec![("part", "snippet".to_string()), ("playlistId", playlist_id.clone())];
+        params.extend(query.params());
+        let request = match (&access_token, &api_key) {
+            (Some(token), _) => client.get(api_url("playlistItems", &params)).bearer_auth(token),
+            (None, Some(key)) => {
+                params.push(("key", key.clone()));
+                client.get(api_url("playlistItems", &params))
+            }
+            (None, None) => return HttpResponse::InternalServerError().body("YOUTUBE_API_KEY non défini"),
+        };
+        match send_json(request, "/playlistItems").await {
+            Ok(body) => Ok(enrich(&client, api_key.as_deref(), &body).await),
+            Err(response) => Err(response),
+        }
+    };
+
+    match page {
+        Ok(page) => {
+            // Les pages consultées anonymement ne sont pas conservées: n'importe qui pourrait
+            // remplir la bibliothèque locale et l'index avec des playlists arbitraires
+            if let Some(token) = &access_token {
+                if let Ok(user_id) = auth::current_user(&store, token).await {
+                    store.remember(Some(&user_id), page.items.clone());
+                }
+            }
+            HttpResponse::Ok().json(page)
+        }
+        Err(response) => response,
+    }
+}
+
+// Id de la playlist des vidéos aimées de l'utilisateur connecté
+async fn likes_playlist_id(client: &Client, access_token: &str) -> Option<String> {
+    let url = api_url("channels", [("part", "contentDetails"), ("mine", "true")]);
+    let channel = send_json(client.get(url).bearer_auth(access_token), "/channels").await.ok()?;
+    channel["items"][0]["contentDetails"]["relatedPlaylists"]["likes"]
+        .as_str()
+        .map(|s| s.to_string())
+}
+
+#[post("/playlists")]
+pub async fn create_playlist(req: HttpRequest, body: web::Json<CreatePlaylistRequest>, store: web::Data<Store>) -> HttpResponse {
+    let access_token = match auth::require_scope(&req, &store, auth::WRITE_SCOPE).await {
+        Ok(token) => token,
+        Err(response) => return response,
+    };
+    let title = body.title.trim();
+    if title.is_empty() {
+        return HttpResponse::BadRequest().body("Le titre de la playlist est obligatoire");
+    }
+    let privacy_status = body.privacy_status.as_deref().unwrap_or("private");
+    if !["private", "unlisted", "public"].contains(&privacy_status) {
+        return HttpResponse::BadRequest().body(format!("privacy_status invalide: {} (valeurs possibles: private, unlisted, public)", privacy_status));
+    }
+
+    let resource = json!({
+        "snippet": { "title": title, "description": body.description },
+        "status": { "privacyStatus": privacy_status },
+    });
+    let url = api_url("playlists", [("part", "snippet,status")]);
+    match send_json(Client::new().post(url).bearer_auth(&access_token).json(&resource), "playlists.insert").await {
+        Ok(item) => match playlist_from_item(&item) {
+            Some(playlist) => {
+                info!("Playlist {} créée", playlist.id);
+                HttpResponse::Created().json(playlist)
+            }
+            None => HttpResponse::BadGateway().body("Réponse de l'API YouTube incomplète"),
+        },
+        Err(response) => response,
+    }
+}
+
+#[post("/playlists/{playlist_id}/items/{video_id}")]
+pub async fn add_playlist_item(req: HttpRequest, path: web::Path<(String, String)>, query: web::Query<PositionQuery>, store: web::Data<Store>) -> HttpResponse {
+    let access_token = match auth::require_scope(&req, &store, auth::WRITE_SCOPE).await {
+        Ok(token) => token,
+        Err(response) => return response,
+    };
+    let (playlist_id, video_id) = path.into_inner();
+    if !is_resource_id(&playlist_id) {
+        return HttpResponse::BadRequest().body(format!("playlist_id invalide: {}", playlist_id));
+    }
+    if !is_video_id(&video_id) {
+        return HttpResponse::BadRequest().body(format!("video_id invalide: {}", video_id));
+    }
+
+    match insert_item(&Client::new(), &access_token, &playlist_id, &video_id, query.position).await {
+        Ok(entry) => HttpResponse::Created().json(entry),
+        Err(response) => response,
+    }
+}
+
+// Ajoute des vidéos du flux à une playlist, une par une: l'API rejette les insertions
+// concurrentes dans une même playlist
+#[post("/playlists/{playlist_id}/videos")]
+pub async fn add_playlist_videos(req: HttpRequest, path: web::Path<String>, body: web::Json<AddVideosRequest>, store: web::Data<Store>) -> HttpResponse {
+    let access_token = match auth::require_scope(&req, &store, auth::WRITE_SCOPE).await {
+        Ok(token) => token,
+        Err(response) => return response,
+    };
+    let playlist_id = path.into_inner();
+    if !is_resource_id(&playlist_id) {
+        return HttpResponse::BadRequest().body(format!("playlist_id invalide: {}", playlist_id));
+    }
+    // Chaque insertion coûte 50 unités de quota
+    if body.video_ids.is_empty() || body.video_ids.len() > MAX_BATCH_VIDEOS {
+        return HttpResponse::BadRequest().body(format!("Entre 1 et {} vidéos par appel", MAX_BATCH_VIDEOS));
+    }
+    if let Some(invalid) = body.video_ids.iter().find(|id| !is_video_id(id)) {
+        return HttpResponse::BadRequest().body(format!("video_id invalide: {}", invalid));
+    }
+
+    let client = Client::new();
+    let mut result = AddVideosResult {
+        added: Vec::new(),
+        failed: Vec::new(),
+    };
+    for video_id in &body.video_ids {
+        match insert_item(&client, &access_token, &playlist_id, video_id, None).await {
+            Ok(entry) => result.added.push(entry),
+            Err(_) => {
+                warn!("Vidéo {} non ajoutée à la playlist {}", video_id, playlist_id);
+                result.failed.push(video_id.clone());
+            }
+        }
+    }
+
+    info!("{} vidéos ajoutées à la playlist {}, {} échecs", result.added.len(), playlist_id, result.failed.len());
+    HttpResponse::Ok().json(result)
+}
+
+// Déplace un élément; playlistItems.update exige de renvoyer la vidéo, relue au préalable
+#[put("/playlists/{playlist_id}/items/{item_id}")]
+pub async fn move_playlist_item(req: HttpRequest, path: web::Path<(String, String)>, body: web::Json<MoveRequest>, store: web::Data<Store>) -> HttpResponse {
+    let access_token = match auth::require_scope(&req, &store, auth::WRITE_SCOPE).await {
+        Ok(token) => token,
+        Err(response) => return response,
+    };
+    let (playlist_id, item_id) = path.into_inner();
+    if !is_resource_id(&playlist_id) || !is_resource_id(&item_id) {
+        return HttpResponse::BadRequest().body("playlist_id ou item_id invalide");
+    }
+    let client = Client::new();
+
+    let url = api_url("playlistItems", [("part", "snippet"), ("id", item_id.as_str())]);
+    let current = match send_json(client.get(url).bearer_auth(&access_token), "/playlistItems").await {
+        Ok(body) => body,
+        Err(response) => return response,
+    };
+    let item = &current["items"][0];
+    if item["snippet"]["playlistId"].as_str() != Some(playlist_id.as_str()) {
+        return HttpResponse::NotFound().body("Élément absent de cette playlist");
+    }
+
+    let resource = json!({
+        "id": item_id,
+        "snippet": {
+            "playlistId": playlist_id,
+            "resourceId": item["snippet"]["resourceId"],
+            "position": body.position,
+        },
+    });
+    let url = api_url("playlistItems", [("part", "snippet")]);
+    match send_json(client.put(url).bearer_auth(&access_token).json(&resource), "playlistItems.update").await {
+        Ok(item) => match entry_from_item(&item) {
+            Some(entry) => HttpResponse::Ok().json(entry),
+            None => HttpResponse::BadGateway().body("Réponse de l'API YouTube incomplète"),
+        },
+        Err(response) => response,
+    }
+}
+
+#[delete("/playlists/{playlist_id}/items/{item_id}")]
+pub async fn remove_playlist_item(req: HttpRequest, path: web::Path<(String, String)>, store: web::Data<Store>) -> HttpResponse {
+    let access_token = match auth::require_scope(&req, &store, auth::WRITE_SCOPE).await {
+        Ok(token) => token,
+        Err(response) => return response,
+    };
+    let (playlist_id, item_id) = path.into_inner();
+    if !is_resource_id(&playlist_id) || !is_resource_id(&item_id) {
+        return HttpResponse::BadRequest().body("playlist_id ou item_id invalide");
+    }
+
+    let url = api_url("playlistItems", [("id", item_id.as_str())]);
+    match send_json(Client::new().delete(url).bearer_auth(&access_token), "playlistItems.delete").await {
+        Ok(_) => {
+            info!("Élément {} retiré de la playlist {}", item_id, playlist_id);
+            HttpResponse::NoContent().finish()
+        }
+        Err(response) => response,
+    }
+}
+
+async fn insert_item(client: &Client, access_token: &str, playlist_id: &str, video_id: &str, position: Option<u32>) -> Result<PlaylistEntry, HttpResponse> {
+    let mut snippet = json!({
+        "playlistId": playlist_id,
+        "resourceId": { "kind": "youtube#video", "videoId": video_id },
+    });
+    if let Some(position) = position {
+        snippet["position"] = json!(position);
+    }
+
+    let url = api_url("playlistItems", [("part", "snippet")]);
+    let item = send_json(client.post(url).bearer_auth(access_token).json(&json!({ "snippet": snippet })), "playlistItems.insert").await?;
+    entry_from_item(&item).ok_or_else(|| HttpResponse::BadGateway().body("Réponse de l'API YouTube incomplète"))
+}
+
+// Ids de playlist et d'élément de playlist: base64 url-safe
+fn is_resource_id(value: &str) -> bool {
+    !value.is_empty() && value.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+fn entry_from_item(item: &Value) -> Option<PlaylistEntry> {
+    Some(PlaylistEntry {
+        item_id: item["id"].as_str()?.to_string(),
+        video_id: item["snippet"]["resourceId"]["videoId"].as_str()?.to_string(),
+        position: item["snippet"]["position"].as_u64(),
+    })
+}
+
+async fn liked_videos(client: &Client, access_token: &str, query: &PageQuery) -> Result<PlaylistItemsPage, HttpResponse> {
+    let mut params = vec![("part", "snippet,contentDetails".to_string()), ("myRating", "like".to_string())];
+    params.extend(query.params());
+    let body = send_json(client.get(api_url("videos", &params)).bearer_auth(access_token), "/videos?myRating=like").await?;
+
+    Ok(PlaylistItemsPage {
+        items: body["items"]
+            .as_array()
+            .map(|items| items.iter().filter_map(video_from_videos_item).collect())
+            .unwrap_or_default(),
+        next_page_token: body["nextPageToken"].as_str().map(|s| s.to_string()),
+        total_results: body["pageInfo"]["totalResults"].as_u64(),
+    })
+}
+
+// Complète les éléments de la playlist (durée, date de publication réelle) en un appel videos.list
+async fn enrich(client: &Client, api_key: Option<&str>, body: &Value) -> PlaylistItemsPage {
+    let mut videos: Vec<Video> = body["items"]
+        .as_array()
+        .map(|items| items.iter().filter_map(video_from_playlist_item).collect())
+        .unwrap_or_default();
+
+    match api_key {
+        Some(api_key) => {
+            let ids: Vec<String> = videos.iter().map(|v| v.video_id.clone()).collect();
+            let details = fetch_videos(client, api_key, &ids).await;
+            info!("{} vidéos de playlist enrichies sur {}", details.len(), ids.len());
+            // Les vidéos privées ou supprimées gardent les informations de la playlist
+            for video in videos.iter_mut() {
+                if let Some(detail) = details.iter().find(|d| d.video_id == video.video_id) {
+                    *video = detail.clone();
+                }
+            }
+        }
+        None => error!("YOUTUBE_API_KEY non défini, vidéos de playlist non enrichies"),
+    }
+
+    PlaylistItemsPage {
+        items: videos,
+        next_page_token: body["nextPageToken"].as_str().map(|s| s.to_string()),
+        total_results: body["pageInfo"]["totalResults"].as_u64(),
+    }
+}
+
+fn playlist_from_item(item: &Value) -> Option<Playlist> {
+    let snippet = &item["snippet"];
+    Some(Playlist {
+        id: item["id"].as_str()?.to_string(),
+        title: snippet["title"].as_str().unwrap_or("Sans titre").to_string(),
+        description: snippet["description"].as_str().unwrap_or("").to_string(),
+        thumbnail: snippet["thumbnails"]["medium"]["url"]
+            .as_str()
+            .or_else(|| snippet["thumbnails"]["default"]["url"].as_str())
+            .unwrap_or("")
+            .to_string(),
+        item_count: item["contentDetails"]["itemCount"].as_u64(),
+        privacy_status: item["status"]["privacyStatus"].as_str().map(|s| s.to_string()),
+        special: None,
+    })
+}
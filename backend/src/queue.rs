@@ -0,0 +1,253 @@
+use actix_web::{delete, get, post, put, web, HttpRequest, HttpResponse};
+use chrono::Utc;
+use log::{error, info};
+use reqwest::Client;
+use serde::Deserialize;
+use std::collections::HashSet;
+use std::env;
+
+use crate::auth;
+use crate::models::Video;
+use crate::store::{QueueItem, Store};
+use crate::subscriptions::fetch_videos;
+
+#[derive(Deserialize, Debug)]
+pub struct AddQuery {
+    // Position d'insertion, à la fin de la file par défaut
+    position: Option<usize>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct ReorderRequest {
+    video_ids: Vec<String>,
+}
+
+// Retourne la file avec des métadonnées rafraîchies en un seul appel videos.list par lot de 50
+#[get("/queue")]
+pub async fn get_queue(req: HttpRequest, store: web::Data<Store>) -> HttpResponse {
+    let user_id = match auth::authenticated_user(&req, &store).await {
+        Ok(id) => id,
+        Err(response) => return response,
+    };
+
+    let video_ids: Vec<String> = store.read(|data| {
+        data.users
+            .get(&user_id)
+            .map(|user| user.queue.iter().map(|item| item.video.video_id.clone()).collect())
+            .unwrap_or_default()
+    });
+
+    if video_ids.is_empty() {
+        return HttpResponse::Ok().json(Vec::<QueueItem>::new());
+    }
+
+    match env::var("YOUTUBE_API_KEY") {
+        Ok(api_key) => {
+            let fresh = fetch_videos(&Client::new(), &api_key, &video_ids).await;
+            // Évite de marquer le stockage comme modifié quand aucun instantané n'a changé
+            let stale = store.read(|data| {
+                data.users
+                    .get(&user_id)
+                    .is_some_and(|user| user.queue.iter().any(|item| fresh_version(item, &fresh).is_some()))
+            });
+            if stale {
+                store.write(|data| {
+                    if let Some(user) = data.users.get_mut(&user_id) {
+                        for item in user.queue.iter_mut() {
+                            if let Some(video) = fresh_version(item, &fresh) {
+                                item.video = video.clone();
+                            }
+                        }
+                    }
+                });
+            }
+        }
+        Err(_) => error!("YOUTUBE_API_KEY non défini, file retournée sans rafraîchissement"),
+    }
+
+    let queue: Vec<QueueItem> = store.read(|data| {
+        data.users
+            .get(&user_id)
+            .map(|user| user.queue.clone())
+            .unwrap_or_default()
+    });
+
+    HttpResponse::Ok().json(queue)
+}
+
+#[post("/queue/{video_id}")]
+pub async fn add_to_queue(req: HttpRequest, path: web::Path<String>, query: web::Query<AddQuery>, store: web::Data<Store>) -> HttpResponse {
+    let user_id = match auth::authenticated_user(&req, &store).await {
+        Ok(id) => id,
+        Err(response) => return response,
+    };
+    let video_id = path.into_inner();
+
+    // Instantané pris dans le stockage local si possible, sinon via l'API
+    let known = store.read(|data| data.find_video(&video_id).cloned());
+    let video = match known {
+        Some(video) => video,
+        None => {
+            let api_key = match env::var("YOUTUBE_API_KEY") {
+                Ok(key) => key,
+                Err(_) => return HttpResponse::InternalServerError().body("YOUTUBE_API_KEY non défini"),
+            };
+            match fetch_videos(&Client::new(), &api_key, std::slice::from_ref(&video_id)).await.pop() {
+                Some(video) => video,
+                None => return HttpResponse::NotFound().body("Vidéo introuvable"),
+            }
+        }
+    };
+
+    let added = store.write(|data| {
+        let queue = &mut data.users.entry(user_id.clone()).or_default().queue;
+        if queue.iter().any(|item| item.video.video_id == video_id) {
+            return false;
+        }
+
+        let position = query.position.unwrap_or(queue.len()).min(queue.len());
+        queue.insert(position, QueueItem {
+            video,
+            added_at: Utc::now(),
+        });
+        true
+    });
+
+    if !added {
+        return HttpResponse::Conflict().body("Vidéo déjà dans la file");
+    }
+
+    info!("Vidéo {} ajoutée à la file de {}", video_id, user_id);
+    HttpResponse::NoContent().finish()
+}
+
+#[delete("/queue/{video_id}")]
+pub async fn remove_from_queue(req: HttpRequest, path: web::Path<String>, store: web::Data<Store>) -> HttpResponse {
+    let user_id = match auth::authenticated_user(&req, &store).await {
+        Ok(id) => id,
+        Err(response) => return response,
+    };
+    let video_id = path.into_inner();
+
+    let removed = store.write(|data| match data.users.get_mut(&user_id) {
+        Some(user) => {
+            let before = user.queue.len();
+            user.queue.retain(|item| item.video.video_id != video_id);
+            user.queue.len() != before
+        }
+        None => false,
+    });
+
+    if removed {
+        HttpResponse::NoContent().finish()
+    } else {
+        HttpResponse::NotFound().body("Vidéo absente de la file")
+    }
+}
+
+// Réordonne la file: la liste doit contenir exactement les vidéos présentes
+#[put("/queue")]
+pub async fn reorder_queue(req: HttpRequest, body: web::Json<ReorderRequest>, store: web::Data<Store>) -> HttpResponse {
+    let user_id = match auth::authenticated_user(&req, &store).await {
+        Ok(id) => id,
+        Err(response) => return response,
+    };
+
+    let reordered = store.write(|data| {
+        let queue = &mut data.users.entry(user_id.clone()).or_default().queue;
+        reorder(queue, &body.video_ids).then(|| queue.clone())
+    });
+
+    match reordered {
+        Some(queue) => HttpResponse::Ok().json(queue),
+        None => HttpResponse::BadRequest().body("La liste doit contenir exactement les vidéos de la file"),
+    }
+}
+
+// Une vidéo devenue privée ou supprimée garde son dernier instantané
+fn fresh_version<'a>(item: &QueueItem, fresh: &'a [Video]) -> Option<&'a Video> {
+    fresh
+        .iter()
+        .find(|v| v.video_id == item.video.video_id)
+        .filter(|v| **v != item.video)
+}
+
+// Laisse la file intacte si la liste contient des doublons, des absents ou des inconnus
+fn reorder(queue: &mut Vec<QueueItem>, video_ids: &[String]) -> bool {
+    let current: HashSet<&String> = queue.iter().map(|item| &item.video.video_id).collect();
+    let requested: HashSet<&String> = video_ids.iter().collect();
+    if current != requested || requested.len() != video_ids.len() {
+        return false;
+    }
+
+    let mut items = std::mem::take(queue);
+    for video_id in video_ids {
+        if let Some(index) = items.iter().position(|item| &item.video.video_id == video_id) {
+            queue.push(items.remove(index));
+        }
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn item(video_id: &str) -> QueueItem {
+        QueueItem {
+            video: Video {
+                url: format!("https://www.youtube.com/watch?v={}", video_id),
+                video_id: video_id.to_string(),
+                published_at: Utc::now(),
+                title: String::new(),
+                thumbnail: String::new(),
+                channel_title: String::new(),
+                channel_id: String::new(),
+                description: String::new(),
+                duration_seconds: None,
+            },
+            added_at: Utc::now(),
+        }
+    }
+
+    fn ids(queue: &[QueueItem]) -> Vec<&str> {
+        queue.iter().map(|item| item.video.video_id.as_str()).collect()
+    }
+
+    fn request(video_ids: &[&str]) -> Vec<String> {
+        video_ids.iter().map(|id| id.to_string()).collect()
+    }
+
+    #[test]
+    fn reorders_queue_in_requested_order() {
+        let mut queue = vec![item("a"), item("b"), item("c")];
+        assert!(reorder(&mut queue, &request(&["c", "a", "b"])));
+        assert_eq!(ids(&queue), vec!["c", "a", "b"]);
+    }
+
+    #[test]
+    fn rejects_invalid_reorder_lists() {
+        let cases: [&[&str]; 4] = [
+            &["a", "b"],
+            &["a", "b", "b", "c"],
+            &["a", "b", "c", "d"],
+            &["a", "b", "d"],
+        ];
+        for video_ids in cases {
+            let mut queue = vec![item("a"), item("b"), item("c")];
+            assert!(!reorder(&mut queue, &request(video_ids)), "{:?}", video_ids);
+            assert_eq!(ids(&queue), vec!["a", "b", "c"]);
+        }
+    }
+
+    #[test]
+    fn only_changed_snapshots_are_stale() {
+        let queued = item("a");
+        let mut updated = queued.video.clone();
+        updated.title = "Nouveau titre".to_string();
+
+        assert!(fresh_version(&queued, &[]).is_none());
+        assert!(fresh_version(&queued, std::slice::from_ref(&queued.video)).is_none());
+        assert_eq!(fresh_version(&queued, &[updated.clone()]), Some(&updated));
+    }
+}
@@ -0,0 +1,167 @@
+use log::info;
+use reqwest::{Client, Url};
+use serde_json::Value;
+use std::error::Error;
+
+use crate::channels::is_channel_id;
+use crate::youtube_api::api_url;
+
+// Pages de youtube.com qui ne sont pas des chaînes: youtube.com/results ne doit pas
+// être résolu comme le handle "results"
+const RESERVED_PATHS: &[&str] = &[
+    "about", "account", "c", "channel", "embed", "feed", "gaming", "hashtag", "history", "kids",
+    "library", "live", "logout", "music", "playlist", "playlists", "premium", "redirect", "results",
+    "shorts", "signin", "subscriptions", "t", "trending", "upload", "user", "watch",
+];
+
+// Ce que désigne la saisie de l'utilisateur, avant interrogation de l'API
+#[derive(Debug, PartialEq)]
+pub enum ChannelRef {
+    Id(String),
+    // "@rustlang" ou youtube.com/@rustlang
+    Handle(String),
+    // youtube.com/user/Foo
+    Username(String),
+    // youtube.com/c/Foo ou youtube.com/Foo: handle ou ancien nom d'utilisateur
+    Custom(String),
+    Video(String),
+    Playlist(String),
+}
+
+// Reconnaît un id, un handle ou un lien YouTube; None pour une recherche libre
+pub fn parse(input: &str) -> Option<ChannelRef> {
+    let input = input.trim();
+
+    if is_channel_id(input) {
+        return Some(ChannelRef::Id(input.to_string()));
+    }
+    if let Some(handle) = input.strip_prefix('@') {
+        return is_name(handle).then(|| ChannelRef::Handle(handle.to_string()));
+    }
+
+    let lower = input.to_ascii_lowercase();
+    if !lower.contains("youtube.com") && !lower.contains("youtu.be") {
+        return None;
+    }
+    let url = if lower.starts_with("http://") || lower.starts_with("https://") {
+        Url::parse(input).ok()?
+    } else {
+        Url::parse(&format!("https://{}", input)).ok()?
+    };
+    parse_url(&url)
+}
+
+fn parse_url(url: &Url) -> Option<ChannelRef> {
+    let host = url.host_str()?.to_ascii_lowercase();
+    let segments: Vec<&str> = url.path_segments()?.filter(|s| !s.is_empty()).collect();
+
+    if host == "youtu.be" {
+        return segments.first().map(|id| ChannelRef::Video(id.to_string()));
+    }
+    if host != "youtube.com" && !host.ends_with(".youtube.com") {
+        return None;
+    }
+
+    let query = |name: &str| url.query_pairs().find(|(k, _)| k == name).map(|(_, v)| v.into_owned());
+    match segments.as_slice() {
+        ["watch", ..] => query("v").map(ChannelRef::Video),
+        ["playlist", ..] => query("list").map(ChannelRef::Playlist),
+        ["shorts" | "live" | "embed", id, ..] => Some(ChannelRef::Video(id.to_string())),
+        ["channel", id, ..] if is_channel_id(id) => Some(ChannelRef::Id(id.to_string())),
+        ["user", name, ..] if is_name(name) => Some(ChannelRef::Username(name.to_string())),
+        ["c", name, ..] if is_name(name) => Some(ChannelRef::Custom(name.to_string())),
+        [first, ..] if first.starts_with('@') && is_name(&first[1..]) => Some(ChannelRef::Handle(first[1..].to_string())),
+        [name] if is_name(name) && !RESERVED_PATHS.contains(&name.to_ascii_lowercase().as_str()) => Some(ChannelRef::Custom(name.to_string())),
+        _ => None,
+    }
+}
+
+fn is_name(value: &str) -> bool {
+    !value.is_empty() && value.chars().all(|c| c.is_alphanumeric() || c == '_' || c == '-' || c == '.')
+}
+
+// Traduit la saisie en id de chaîne canonique; Ok(None) si elle doit passer par la recherche
+pub async fn resolve(client: &Client, api_key: &str, input: &str) -> Result<Option<String>, Box<dyn Error>> {
+    let reference = match parse(input) {
+        Some(reference) => reference,
+        None => return Ok(None),
+    };
+    info!("Résolution de {:?}", reference);
+
+    let channel_id = match reference {
+        ChannelRef::Id(id) => Some(id),
+        ChannelRef::Handle(handle) => lookup(client, api_key, "channels", "forHandle", &handle, "/id").await?,
+        ChannelRef::Username(name) => lookup(client, api_key, "channels", "forUsername", &name, "/id").await?,
+        ChannelRef::Custom(name) => match lookup(client, api_key, "channels", "forHandle", &name, "/id").await? {
+            Some(id) => Some(id),
+            None => lookup(client, api_key, "channels", "forUsername", &name, "/id").await?,
+        },
+        ChannelRef::Video(id) => lookup(client, api_key, "videos", "id", &id, "/snippet/channelId").await?,
+        ChannelRef::Playlist(id) => lookup(client, api_key, "playlists", "id", &id, "/snippet/channelId").await?,
+    };
+    Ok(channel_id)
+}
+
+// Premier élément de `endpoint?{filter}={value}`, dont on extrait le champ `pointer`
+async fn lookup(client: &Client, api_key: &str, endpoint: &str, filter: &str, value: &str, pointer: &str) -> Result<Option<String>, Box<dyn Error>> {
+    let part = if pointer == "/id" { "id" } else { "snippet" };
+    let url = api_url(endpoint, [("part", part), (filter, value), ("key", api_key)]);
+    let res: Value = client.get(url).send().await?.error_for_status()?.json().await?;
+
+    Ok(res["items"]
+        .as_array()
+        .and_then(|items| items.first())
+        .and_then(|item| item.pointer(pointer))
+        .and_then(|id| id.as_str())
+        .map(|id| id.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_channel_references() {
+        let id = "UCaYhcUwRBNscFNUKTjgPFiA";
+        let cases: Vec<(&str, Option<ChannelRef>)> = vec![
+            (id, Some(ChannelRef::Id(id.to_string()))),
+            ("  UCaYhcUwRBNscFNUKTjgPFiA ", Some(ChannelRef::Id(id.to_string()))),
+            ("@rustlang", Some(ChannelRef::Handle("rustlang".to_string()))),
+            ("@", None),
+            ("https://www.youtube.com/@rustlang", Some(ChannelRef::Handle("rustlang".to_string()))),
+            ("youtube.com/@rustlang/videos", Some(ChannelRef::Handle("rustlang".to_string()))),
+            ("https://www.youtube.com/channel/UCaYhcUwRBNscFNUKTjgPFiA", Some(ChannelRef::Id(id.to_string()))),
+            ("https://m.youtube.com/channel/UCaYhcUwRBNscFNUKTjgPFiA/featured", Some(ChannelRef::Id(id.to_string()))),
+            ("https://www.youtube.com/channel/pas-un-id", None),
+            ("https://www.youtube.com/user/Google", Some(ChannelRef::Username("Google".to_string()))),
+            ("https://www.youtube.com/c/RustVideos", Some(ChannelRef::Custom("RustVideos".to_string()))),
+            ("https://www.youtube.com/RustVideos", Some(ChannelRef::Custom("RustVideos".to_string()))),
+            ("https://www.youtube.com/watch?v=dQw4w9WgXcQ&t=42s", Some(ChannelRef::Video("dQw4w9WgXcQ".to_string()))),
+            ("https://youtu.be/dQw4w9WgXcQ", Some(ChannelRef::Video("dQw4w9WgXcQ".to_string()))),
+            ("https://www.youtube.com/shorts/dQw4w9WgXcQ", Some(ChannelRef::Video("dQw4w9WgXcQ".to_string()))),
+            ("https://www.youtube.com/playlist?list=PLx0sYbCqOb8TBPRdmBHs5Iftvv9TPboYG", Some(ChannelRef::Playlist("PLx0sYbCqOb8TBPRdmBHs5Iftvv9TPboYG".to_string()))),
+            ("https://notyoutube.com/@rustlang", None),
+            ("tutoriel rust", None),
+        ];
+        for (input, expected) in cases {
+            assert_eq!(parse(input), expected, "{}", input);
+        }
+    }
+
+    #[test]
+    fn ignores_reserved_paths() {
+        for input in [
+            "https://www.youtube.com/results?search_query=rust",
+            "https://www.youtube.com/feed",
+            "https://www.youtube.com/feed/subscriptions",
+            "https://www.youtube.com/playlist",
+            "https://www.youtube.com/shorts",
+            "https://www.youtube.com/about",
+            "https://www.youtube.com/Trending",
+            "https://www.youtube.com/watch",
+            "https://www.youtube.com/",
+        ] {
+            assert_eq!(parse(input), None, "{}", input);
+        }
+    }
+}
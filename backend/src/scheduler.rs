@@ -0,0 +1,411 @@
+use actix_web::web;
+use chrono::{DateTime, Duration, Utc};
+use futures::stream::{self, StreamExt};
+use log::{error, info, warn};
+use rand::Rng;
+use reqwest::Client;
+use std::collections::HashSet;
+use std::env;
+use tokio::sync::watch;
+
+use crate::auth::oauth_client;
+use crate::captions::fetch_public_transcript;
+use crate::models::{SavedToken, Video};
+use crate::store::{ChannelSchedule, Store, StoreData};
+use crate::subscriptions::{fetch_subscription_channel_ids, fetch_uploads_playlists, refresh_oauth_token, try_fetch_playlist_videos};
+use crate::websub;
+
+const TICK: std::time::Duration = std::time::Duration::from_secs(60);
+// Fréquence de rafraîchissement de la liste des abonnements de chaque utilisateur
+pub const USER_REFRESH_HOURS: i64 = 6;
+// Délai avant de retenter un utilisateur dont le rafraîchissement a échoué
+const USER_RETRY_HOURS: i64 = 1;
+// Délai avant de redemander la playlist d'uploads d'une chaîne qui n'en avait pas
+const UPLOADS_RETRY_HOURS: i64 = 24;
+// Délai avant de réinterroger une chaîne dont la récupération a échoué
+const POLL_RETRY_MINUTES: i64 = 5;
+// Les vidéos du flux plus anciennes sont retirées du stockage
+const VIDEO_RETENTION_DAYS: i64 = 90;
+const MAX_CONCURRENT_POLLS: usize = 8;
+const JITTER_RATIO: f64 = 0.1;
+// Transcriptions récupérées par tour, pour étaler les appels à timedtext
+const TRANSCRIPTS_PER_TICK: usize = 10;
+
+// Tâche de fond: rafraîchit abonnements et uploads jusqu'à l'arrêt du serveur
+pub async fn run(store: web::Data<Store>, mut shutdown: watch::Receiver<bool>) {
+    let api_key = match env::var("YOUTUBE_API_KEY") {
+        Ok(key) => key,
+        Err(_) => {
+            warn!("YOUTUBE_API_KEY non défini, planificateur désactivé");
+            return;
+        }
+    };
+
+    let client = Client::new();
+    let mut interval = tokio::time::interval(TICK);
+
+    loop {
+        tokio::select! {
+            _ = interval.tick() => {}
+            _ = shutdown.changed() => break,
+        }
+
+        // Un tour en cours est abandonné si le serveur s'arrête
+        tokio::select! {
+            _ = run_once(&client, &api_key, &store) => {}
+            _ = shutdown.changed() => break,
+        }
+    }
+
+    info!("Arrêt du planificateur");
+}
+
+async fn run_once(client: &Client, api_key: &str, store: &web::Data<Store>) {
+    refresh_users(client, store).await;
+    sync_channel_schedules(store);
+    resolve_uploads_playlists(client, api_key, store).await;
+    poll_due_channels(client, api_key, store).await;
+    fetch_missing_transcripts(client, store).await;
+    prune_old_videos(store);
+}
+
+// Retire du flux les vidéos publiées avant la période de rétention, avec leur transcription
+fn prune_old_videos(store: &Store) {
+    let cutoff = Utc::now() - Duration::days(VIDEO_RETENTION_DAYS);
+    let expired: Vec<String> = store.read(|data| {
+        data.videos
+            .values()
+            .filter(|entry| entry.video.published_at < cutoff)
+            .map(|entry| entry.video.video_id.clone())
+            .collect()
+    });
+    if expired.is_empty() {
+        return;
+    }
+
+    info!("{} vidéos de plus de {} jours retirées du stockage", expired.len(), VIDEO_RETENTION_DAYS);
+    store.remove_videos(&expired);
+}
+
+// Chaînes de l'utilisateur si le planificateur les tient à jour: abonnements rafraîchis
+// récemment et chaque chaîne déjà interrogée au moins une fois
+pub fn fresh_channel_ids(data: &StoreData, user_id: &str, now: DateTime<Utc>) -> Option<Vec<String>> {
+    let user = data.users.get(user_id)?;
+    let refreshed_at = user.refreshed_at?;
+    if refreshed_at + Duration::hours(USER_REFRESH_HOURS) <= now {
+        return None;
+    }
+    let polled = user.channel_ids.iter().all(|channel_id| {
+        data.channels
+            .get(channel_id)
+            .is_some_and(|schedule| schedule.last_polled_at.is_some() || schedule.uploads_missing_at.is_some())
+    });
+    polled.then(|| user.channel_ids.clone())
+}
+
+// Indexe la transcription des vidéos du flux les plus récentes, si TRANSCRIPT_LANGS est défini ("fr,en")
+async fn fetch_missing_transcripts(client: &Client, store: &web::Data<Store>) {
+    let languages: Vec<String> = env::var("TRANSCRIPT_LANGS")
+        .unwrap_or_default()
+        .split(',')
+        .map(|lang| lang.trim().to_string())
+        .filter(|lang| !lang.is_empty())
+        .collect();
+    if languages.is_empty() {
+        return;
+    }
+
+    let missing: Vec<String> = store.read(|data| {
+        let mut videos: Vec<&Video> = data
+            .videos
+            .values()
+            .map(|entry| &entry.video)
+            .filter(|video| !data.transcripts.contains_key(&video.video_id))
+            .collect();
+        videos.sort_by_key(|video| std::cmp::Reverse(video.published_at));
+        videos.into_iter().take(TRANSCRIPTS_PER_TICK).map(|video| video.video_id.clone()).collect()
+    });
+
+    let mut fetched = Vec::new();
+    for video_id in missing {
+        match fetch_public_transcript(client, &video_id, &languages).await {
+            Ok(transcript) => {
+                info!("Transcription de {}: {} segments", video_id, transcript.segments.len());
+                fetched.push((video_id, transcript));
+            }
+            Err(e) => {
+                error!("Transcription de {} non récupérée: {}", video_id, e);
+                break;
+            }
+        }
+    }
+    // Une seule écriture par tour, donc une seule sauvegarde du fichier
+    store.save_transcripts(fetched);
+}
+
+async fn refresh_users(client: &Client, store: &web::Data<Store>) {
+    let now = Utc::now();
+    let due: Vec<(String, SavedToken)> = store.read(|data| {
+        data.users
+            .iter()
+            .filter(|(_, user)| user.refreshed_at.is_none_or(|t| t + Duration::hours(USER_REFRESH_HOURS) <= now))
+            .filter(|(_, user)| user.refresh_failed_at.is_none_or(|t| t + Duration::hours(USER_RETRY_HOURS) <= now))
+            .filter_map(|(user_id, user)| user.token.clone().map(|token| (user_id.clone(), token)))
+            .collect()
+    });
+
+    for (user_id, token) in due {
+        let token = match ensure_fresh_token(token).await {
+            Ok(token) => token,
+            Err(e) => {
+                error!("Rafraîchissement impossible pour l'utilisateur {}: {}", user_id, e);
+                record_refresh_failure(store, &user_id, now);
+                continue;
+            }
+        };
+
+        match fetch_subscription_channel_ids(client, &token.access_token).await {
+            Ok(channel_ids) => {
+                info!("Abonnements de {} rafraîchis: {} chaînes", user_id, channel_ids.len());
+                store.write(|data| {
+                    let user = data.users.entry(user_id.clone()).or_default();
+                    user.channel_ids = channel_ids.clone();
+                    user.token = Some(token);
+                    user.refreshed_at = Some(now);
+                    user.refresh_failed_at = None;
+                });
+                tokio::spawn(websub::ensure_subscribed(store.clone(), channel_ids));
+            }
+            Err(e) => {
+                error!("Abonnements de {} non rafraîchis: {}", user_id, e);
+                record_refresh_failure(store, &user_id, now);
+            }
+        }
+    }
+}
+
+// Sans cela un token révoqué serait retenté à chaque tour
+fn record_refresh_failure(store: &Store, user_id: &str, now: DateTime<Utc>) {
+    store.write(|data| {
+        if let Some(user) = data.users.get_mut(user_id) {
+            user.refresh_failed_at = Some(now);
+        }
+    });
+}
+
+async fn ensure_fresh_token(token: SavedToken) -> Result<SavedToken, String> {
+    let expires_in = token.expires_in.unwrap_or(3600) as i64;
+    if Utc::now() < token.issued_at + Duration::seconds(expires_in - 300) {
+        return Ok(token);
+    }
+
+    let refresh_token = token.refresh_token.ok_or("Aucun refresh_token disponible")?;
+    let mut fresh = refresh_oauth_token(&oauth_client(), &refresh_token).await?;
+    // Google ne renvoie pas toujours un nouveau refresh_token
+    if fresh.refresh_token.is_none() {
+        fresh.refresh_token = Some(refresh_token);
+    }
+    Ok(fresh)
+}
+
+// Aligne les chaînes planifiées sur l'union des abonnements connus
+fn sync_channel_schedules(store: &Store) {
+    store.write(|data| {
+        let followed: HashSet<String> = data.users
+            .values()
+            .flat_map(|user| user.channel_ids.iter().cloned())
+            .collect();
+
+        data.channels.retain(|channel_id, _| followed.contains(channel_id));
+        for channel_id in followed {
+            data.channels.entry(channel_id).or_insert_with(ChannelSchedule::default);
+        }
+    });
+}
+
+async fn resolve_uploads_playlists(client: &Client, api_key: &str, store: &Store) {
+    let now = Utc::now();
+    let missing: Vec<String> = store.read(|data| {
+        data.channels
+            .iter()
+            .filter(|(_, schedule)| schedule.uploads_playlist.is_none())
+            .filter(|(_, schedule)| schedule.uploads_missing_at.is_none_or(|t| t + Duration::hours(UPLOADS_RETRY_HOURS) <= now))
+            .map(|(channel_id, _)| channel_id.clone())
+            .collect()
+    });
+
+    if missing.is_empty() {
+        return;
+    }
+
+    let uploads = fetch_uploads_playlists(client, api_key, &missing).await;
+    store.write(|data| {
+        for channel_id in &missing {
+            if let Some(schedule) = data.channels.get_mut(channel_id) {
+                match uploads.iter().find(|(id, _)| id == channel_id) {
+                    Some((_, pid)) => {
+                        schedule.uploads_playlist = Some(pid.clone());
+                        schedule.uploads_missing_at = None;
+                    }
+                    // Mémorisé pour ne pas redemander la chaîne à chaque tour
+                    None => schedule.uploads_missing_at = Some(now),
+                }
+            }
+        }
+    });
+}
+
+async fn poll_due_channels(client: &Client, api_key: &str, store: &Store) {
+    let now = Utc::now();
+    let due: Vec<(String, String)> = store.read(|data| {
+        data.channels
+            .iter()
+            .filter(|(_, schedule)| schedule.next_poll_at.is_none_or(|t| t <= now))
+            .filter_map(|(channel_id, schedule)| {
+                schedule.uploads_playlist.clone().map(|pid| (channel_id.clone(), pid))
+            })
+            .collect()
+    });
+
+    if due.is_empty() {
+        return;
+    }
+
+    info!("Rafraîchissement planifié de {} chaînes", due.len());
+    let results: Vec<(String, Result<Vec<Video>, String>)> = stream::iter(due)
+        .map(|(channel_id, pid)| async move {
+            let videos = try_fetch_playlist_videos(client, api_key, &pid, 5).await;
+            (channel_id, videos)
+        })
+        .buffer_unordered(MAX_CONCURRENT_POLLS)
+        .collect()
+        .await;
+
+    let mut polled: Vec<String> = Vec::new();
+    let mut failed: Vec<String> = Vec::new();
+    let mut videos: Vec<Video> = Vec::new();
+    for (channel_id, result) in results {
+        match result {
+            Ok(fetched) => {
+                polled.push(channel_id);
+                videos.extend(fetched);
+            }
+            Err(e) => {
+                error!("Chaîne {} non rafraîchie: {}", channel_id, e);
+                failed.push(channel_id);
+            }
+        }
+    }
+    let new_count = store.ingest(videos);
+    if new_count > 0 {
+        info!("{} nouvelles vidéos trouvées par le planificateur", new_count);
+    }
+
+    store.write(|data| {
+        for channel_id in polled {
+            let interval = poll_interval(data.latest_upload(&channel_id), now);
+            if let Some(schedule) = data.channels.get_mut(&channel_id) {
+                schedule.last_polled_at = Some(now);
+                schedule.next_poll_at = Some(now + with_jitter(interval));
+            }
+        }
+        // Une erreur passagère ne doit pas repousser la chaîne d'un intervalle complet
+        for channel_id in failed {
+            if let Some(schedule) = data.channels.get_mut(&channel_id) {
+                schedule.next_poll_at = Some(now + Duration::minutes(POLL_RETRY_MINUTES));
+            }
+        }
+    });
+}
+
+// Les chaînes qui publient souvent sont interrogées plus fréquemment
+fn poll_interval(latest_upload: Option<DateTime<Utc>>, now: DateTime<Utc>) -> Duration {
+    match latest_upload.map(|date| now - date) {
+        Some(age) if age < Duration::days(1) => Duration::minutes(15),
+        Some(age) if age < Duration::days(7) => Duration::hours(1),
+        Some(age) if age < Duration::days(30) => Duration::hours(6),
+        _ => Duration::hours(24),
+    }
+}
+
+// Étale les requêtes pour éviter que toutes les chaînes soient interrogées au même moment
+fn with_jitter(interval: Duration) -> Duration {
+    let factor = rand::thread_rng().gen_range(-JITTER_RATIO..=JITTER_RATIO);
+    interval + Duration::milliseconds((interval.num_milliseconds() as f64 * factor) as i64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::UserData;
+
+    fn store_data(refreshed_hours_ago: i64, polled: bool) -> (StoreData, DateTime<Utc>) {
+        let now = Utc::now();
+        let mut data = StoreData::default();
+        data.users.insert(
+            "UC_user".to_string(),
+            UserData {
+                channel_ids: vec!["UC_a".to_string(), "UC_b".to_string()],
+                refreshed_at: Some(now - Duration::hours(refreshed_hours_ago)),
+                ..UserData::default()
+            },
+        );
+        for channel_id in ["UC_a", "UC_b"] {
+            data.channels.insert(
+                channel_id.to_string(),
+                ChannelSchedule {
+                    last_polled_at: polled.then_some(now),
+                    ..ChannelSchedule::default()
+                },
+            );
+        }
+        (data, now)
+    }
+
+    #[test]
+    fn polls_active_channels_more_often() {
+        let now = Utc::now();
+        let cases = [
+            (Some(now - Duration::hours(2)), Duration::minutes(15)),
+            (Some(now - Duration::days(3)), Duration::hours(1)),
+            (Some(now - Duration::days(10)), Duration::hours(6)),
+            (Some(now - Duration::days(60)), Duration::hours(24)),
+            (None, Duration::hours(24)),
+        ];
+        for (latest_upload, expected) in cases {
+            assert_eq!(poll_interval(latest_upload, now), expected, "{:?}", latest_upload);
+        }
+    }
+
+    #[test]
+    fn keeps_jitter_within_bounds() {
+        let interval = Duration::hours(1);
+        let margin = Duration::milliseconds((interval.num_milliseconds() as f64 * JITTER_RATIO) as i64);
+        for _ in 0..1000 {
+            let jittered = with_jitter(interval);
+            assert!(jittered >= interval - margin && jittered <= interval + margin, "{:?}", jittered);
+        }
+    }
+
+    #[test]
+    fn serves_feed_from_store_when_fresh() {
+        let (data, now) = store_data(1, true);
+        assert_eq!(fresh_channel_ids(&data, "UC_user", now), Some(vec!["UC_a".to_string(), "UC_b".to_string()]));
+        assert_eq!(fresh_channel_ids(&data, "UC_inconnu", now), None);
+    }
+
+    #[test]
+    fn refetches_stale_or_unpolled_feeds() {
+        let (data, now) = store_data(USER_REFRESH_HOURS, true);
+        assert_eq!(fresh_channel_ids(&data, "UC_user", now), None);
+
+        let (data, now) = store_data(1, false);
+        assert_eq!(fresh_channel_ids(&data, "UC_user", now), None);
+
+        // Une chaîne sans playlist d'uploads ne sera jamais interrogée et ne bloque pas le cache
+        let (mut data, now) = store_data(1, true);
+        data.channels.get_mut("UC_b").unwrap().last_polled_at = None;
+        data.channels.get_mut("UC_b").unwrap().uploads_missing_at = Some(now);
+        assert!(fresh_channel_ids(&data, "UC_user", now).is_some());
+    }
+}
@@ -0,0 +1,237 @@
+use actix_web::http::StatusCode;
+use chrono::{DateTime, Duration, Utc};
+use futures::future::{BoxFuture, FutureExt, Shared};
+use log::info;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::env;
+use std::future::Future;
+use std::sync::{Mutex, RwLock};
+
+use crate::search_video::SearchPage;
+use crate::store::Store;
+
+const DEFAULT_TTL_SECONDS: i64 = 900;
+const MAX_ENTRIES: usize = 500;
+
+// Erreur renvoyée telle quelle au client, partagée entre les requêtes regroupées
+#[derive(Clone, Debug)]
+pub struct SearchError {
+    pub status: StatusCode,
+    pub message: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct CachedSearch {
+    pub page: SearchPage,
+    pub fetched_at: DateTime<Utc>,
+}
+
+type PendingSearch = Shared<BoxFuture<'static, Result<CachedSearch, SearchError>>>;
+
+// Cache des résultats de /search/{query}; les recherches identiques simultanées
+// ne déclenchent qu'un seul appel à l'API
+pub struct SearchCache {
+    ttl: Duration,
+    // Conserve aussi les entrées dans le fichier de stockage (SEARCH_CACHE_PERSIST)
+    persist: bool,
+    entries: RwLock<HashMap<String, CachedSearch>>,
+    in_flight: Mutex<HashMap<String, PendingSearch>>,
+}
+
+impl SearchCache {
+    pub fn from_env(store: &Store) -> SearchCache {
+        let ttl_seconds = env::var("SEARCH_CACHE_TTL_SECONDS")
+            .ok()
+            .and_then(|v| v.parse::<i64>().ok())
+            .filter(|ttl| *ttl >= 0)
+            .unwrap_or(DEFAULT_TTL_SECONDS);
+        let persist = env::var("SEARCH_CACHE_PERSIST").is_ok_and(|v| v == "1" || v == "true");
+
+        let ttl = Duration::seconds(ttl_seconds);
+        let now = Utc::now();
+        let entries: HashMap<String, CachedSearch> = if persist {
+            store.read(|data| {
+                data.search_cache
+                    .iter()
+                    .filter(|(_, entry)| now - entry.fetched_at < ttl)
+                    .map(|(key, entry)| (key.clone(), entry.clone()))
+                    .collect()
+            })
+        } else {
+            HashMap::new()
+        };
+        info!("Cache de recherche: TTL {}s, {} entrées reprises", ttl_seconds, entries.len());
+
+        SearchCache {
+            ttl,
+            persist,
+            entries: RwLock::new(entries),
+            in_flight: Mutex::new(HashMap::new()),
+        }
+    }
+
+    // Clé normalisée: requête en minuscules aux espaces réduits, puis paramètres triés
+    pub fn key(query: &str, params: &[(&'static str, String)]) -> String {
+        let query = query.split_whitespace().collect::<Vec<_>>().join(" ").to_lowercase();
+        let mut params: Vec<String> = params.iter().map(|(name, value)| format!("{}={}", name, value)).collect();
+        params.sort();
+        format!("{}\u{1f}{}", query, params.join("&"))
+    }
+
+    // Âge et durée de validité restante d'une entrée, en secondes
+    pub fn freshness(&self, entry: &CachedSearch) -> (i64, i64) {
+        let age = (Utc::now() - entry.fetched_at).num_seconds().max(0);
+        (age, (self.ttl.num_seconds() - age).max(0))
+    }
+
+    fn fresh(&self, key: &str) -> Option<CachedSearch> {
+        let entries = self.entries.read().unwrap();
+        entries
+            .get(key)
+            .filter(|entry| Utc::now() - entry.fetched_at < self.ttl)
+            .cloned()
+    }
+
+    // Retourne l'entrée en cache si elle est valide, sinon rejoint la recherche
+    // déjà en cours pour la même clé ou en lance une nouvelle
+    pub async fn get_or_fetch<F>(&self, store: &Store, key: String, fetch: F) -> Result<CachedSearch, SearchError>
+    where
+        F: Future<Output = Result<SearchPage, SearchError>> + Send + 'static,
+    {
+        if let Some(entry) = self.fresh(&key) {
+            return Ok(entry);
+        }
+
+        let pending = {
+            let mut in_flight = self.in_flight.lock().unwrap();
+            // Une recherche a pu se terminer entre la lecture du cache et la prise du verrou
+            if let Some(entry) = self.fresh(&key) {
+                return Ok(entry);
+            }
+            in_flight
+                .entry(key.clone())
+                .or_insert_with(|| {
+                    async move {
+                        fetch.await.map(|page| CachedSearch {
+                            page,
+                            fetched_at: Utc::now(),
+                        })
+                    }
+                    .boxed()
+                    .shared()
+                })
+                .clone()
+        };
+
+        let result = pending.clone().await;
+        self.complete(store, key, &pending, &result);
+        result
+    }
+
+    // Seule la première requête à terminer enregistre le résultat; une requête en retard
+    // ne doit pas retirer une recherche plus récente lancée pour la même clé
+    fn complete(&self, store: &Store, key: String, pending: &PendingSearch, result: &Result<CachedSearch, SearchError>) {
+        let mut in_flight = self.in_flight.lock().unwrap();
+        if in_flight.get(&key).is_some_and(|current| current.ptr_eq(pending)) {
+            in_flight.remove(&key);
+            if let Ok(entry) = result {
+                self.insert(store, key, entry.clone());
+            }
+        }
+    }
+
+    fn insert(&self, store: &Store, key: String, entry: CachedSearch) {
+        if self.ttl.is_zero() {
+            return;
+        }
+
+        let now = Utc::now();
+        let mut entries = self.entries.write().unwrap();
+        entries.retain(|_, e| now - e.fetched_at < self.ttl);
+        if entries.len() >= MAX_ENTRIES {
+            if let Some(oldest) = entries.iter().min_by_key(|(_, e)| e.fetched_at).map(|(k, _)| k.clone()) {
+                entries.remove(&oldest);
+            }
+        }
+        entries.insert(key, entry);
+
+        if self.persist {
+            store.write(|data| data.search_cache = entries.clone());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    fn cache() -> SearchCache {
+        SearchCache {
+            ttl: Duration::seconds(DEFAULT_TTL_SECONDS),
+            persist: false,
+            entries: RwLock::new(HashMap::new()),
+            in_flight: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn page() -> SearchPage {
+        SearchPage {
+            items: Vec::new(),
+            next_page_token: Some("CAUQAA".to_string()),
+            prev_page_token: None,
+            total_results: Some(42),
+        }
+    }
+
+    async fn counted_fetch(calls: Arc<AtomicUsize>) -> Result<SearchPage, SearchError> {
+        calls.fetch_add(1, Ordering::SeqCst);
+        tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+        Ok(page())
+    }
+
+    #[tokio::test]
+    async fn concurrent_lookups_share_one_fetch() {
+        let cache = cache();
+        let store = Store::in_memory();
+        let calls = Arc::new(AtomicUsize::new(0));
+        let key = SearchCache::key("rust", &[]);
+
+        let (first, second) = tokio::join!(
+            cache.get_or_fetch(&store, key.clone(), counted_fetch(calls.clone())),
+            cache.get_or_fetch(&store, key.clone(), counted_fetch(calls.clone())),
+        );
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+        assert_eq!(first.unwrap().page.total_results, Some(42));
+        assert_eq!(second.unwrap().page.total_results, Some(42));
+        assert!(cache.in_flight.lock().unwrap().is_empty());
+
+        // Servie ensuite depuis le cache
+        cache.get_or_fetch(&store, key, counted_fetch(calls.clone())).await.unwrap();
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn late_request_keeps_newer_in_flight_search() {
+        let cache = cache();
+        let store = Store::in_memory();
+        let key = SearchCache::key("rust", &[]);
+        let pending = || -> PendingSearch { async { Ok(CachedSearch { page: page(), fetched_at: Utc::now() }) }.boxed().shared() };
+
+        // L'ancienne recherche a déjà été retirée et une nouvelle a été lancée pour la même clé
+        let stale = pending();
+        let newer = pending();
+        cache.in_flight.lock().unwrap().insert(key.clone(), newer.clone());
+
+        let result = Ok(CachedSearch { page: page(), fetched_at: Utc::now() });
+        cache.complete(&store, key.clone(), &stale, &result);
+        assert!(cache.in_flight.lock().unwrap().get(&key).unwrap().ptr_eq(&newer));
+        assert!(cache.fresh(&key).is_none());
+
+        cache.complete(&store, key.clone(), &newer, &result);
+        assert!(cache.in_flight.lock().unwrap().is_empty());
+        assert!(cache.fresh(&key).is_some());
+    }
+}
@@ -1,15 +1,25 @@
-use actix_web::{get, web,  HttpResponse, Responder};
+use actix_web::http::{header, StatusCode};
+use actix_web::{get, web, HttpRequest, HttpResponse, Responder};
+use chrono::{DateTime, SecondsFormat, Utc};
 use serde::{Deserialize, Serialize};
 use std::env;
 
+use crate::auth;
+use crate::channels::is_channel_id;
+use crate::models;
+use crate::search_cache::{SearchCache, SearchError};
+use crate::store::Store;
+use crate::youtube_api::api_url;
+
 // Structure de sortie finale (inchangée)
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct Video {
     pub video_id: String,
     pub title: String,
     pub description: String,
     pub thumbnail: String,
     pub channel_title: String,
+    pub channel_id: String,
     pub published_at: String,
     pub duration: String,
     pub view_count: String,
@@ -29,8 +39,28 @@ struct SearchResultId {
 }
 
 #[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
 struct SearchResponse {
     items: Vec<SearchResultItem>,
+    next_page_token: Option<String>,
+    prev_page_token: Option<String>,
+    #[serde(default)]
+    page_info: PageInfo,
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+struct PageInfo {
+    total_results: Option<u64>,
+}
+
+// Enveloppe renvoyée au frontend pour charger les pages suivantes
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct SearchPage {
+    pub items: Vec<Video>,
+    pub next_page_token: Option<String>,
+    pub prev_page_token: Option<String>,
+    pub total_results: Option<u64>,
 }
 
 // --- Structures pour l'endpoint /videos (Étape 2) (inchangées) ---
@@ -54,6 +84,8 @@ struct VideoSnippet {
     description: String,
     thumbnails: Thumbnails,
     channel_title: String,
+    #[serde(default)]
+    channel_id: String,
 }
 #[derive(Deserialize, Debug)]
 struct Thumbnails {
@@ -74,9 +106,127 @@ struct Statistics {
     view_count: String,
 }
 
+// --- Paramètres avancés de recherche (query string) ---
+#[derive(Deserialize, Debug)]
+pub struct SearchParams {
+    order: Option<String>,
+    published_after: Option<String>,
+    published_before: Option<String>,
+    video_duration: Option<String>,
+    video_definition: Option<String>,
+    event_type: Option<String>,
+    region_code: Option<String>,
+    relevance_language: Option<String>,
+    safe_search: Option<String>,
+    channel_id: Option<String>,
+    page_token: Option<String>,
+}
+
+impl SearchParams {
+    // Valide les paramètres et les traduit en paramètres de l'API search.list
+    pub fn to_api_params(&self) -> Result<Vec<(&'static str, String)>, String> {
+        let mut params = Vec::new();
+
+        if let Some(order) = &self.order {
+            params.push(("order", one_of("order", order, &["date", "rating", "relevance", "title", "viewCount"])?));
+        }
+
+        let published_after = self.published_after.as_deref().map(|d| parse_date("published_after", d)).transpose()?;
+        let published_before = self.published_before.as_deref().map(|d| parse_date("published_before", d)).transpose()?;
+        if let (Some(after), Some(before)) = (published_after, published_before) {
+            if after >= before {
+                return Err("published_after doit précéder published_before".to_string());
+            }
+        }
+        if let Some(after) = published_after {
+            params.push(("publishedAfter", after.to_rfc3339_opts(SecondsFormat::Secs, true)));
+        }
+        if let Some(before) = published_before {
+            params.push(("publishedBefore", before.to_rfc3339_opts(SecondsFormat::Secs, true)));
+        }
+
+        if let Some(duration) = &self.video_duration {
+            params.push(("videoDuration", one_of("video_duration", duration, &["any", "short", "medium", "long"])?));
+        }
+        if let Some(definition) = &self.video_definition {
+            params.push(("videoDefinition", one_of("video_definition", definition, &["any", "high", "standard"])?));
+        }
+        if let Some(event_type) = &self.event_type {
+            params.push(("eventType", one_of("event_type", event_type, &["completed", "live", "upcoming"])?));
+        }
+        if let Some(safe_search) = &self.safe_search {
+            params.push(("safeSearch", one_of("safe_search", safe_search, &["moderate", "none", "strict"])?));
+        }
+
+        if let Some(region) = &self.region_code {
+            if region.len() != 2 || !region.chars().all(|c| c.is_ascii_alphabetic()) {
+                return Err(format!("region_code invalide (code pays ISO 3166-1 alpha-2 attendu): {}", region));
+            }
+            params.push(("regionCode", region.to_ascii_uppercase()));
+        }
+
+        if let Some(language) = &self.relevance_language {
+            if !is_language_code(language) {
+                return Err(format!("relevance_language invalide (code ISO 639-1 attendu): {}", language));
+            }
+            params.push(("relevanceLanguage", language.clone()));
+        }
+
+        if let Some(channel_id) = &self.channel_id {
+            if !is_channel_id(channel_id) {
+                return Err(format!("channel_id invalide: {}", channel_id));
+            }
+            params.push(("channelId", channel_id.clone()));
+        }
+
+        if let Some(page_token) = &self.page_token {
+            let valid = !page_token.is_empty()
+                && page_token.len() <= 128
+                && page_token.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+            if !valid {
+                return Err(format!("page_token invalide: {}", page_token));
+            }
+            params.push(("pageToken", page_token.clone()));
+        }
+
+        Ok(params)
+    }
+}
+
+fn one_of(name: &str, value: &str, allowed: &[&str]) -> Result<String, String> {
+    if allowed.contains(&value) {
+        Ok(value.to_string())
+    } else {
+        Err(format!("{} invalide: {} (valeurs possibles: {})", name, value, allowed.join(", ")))
+    }
+}
+
+fn parse_date(name: &str, value: &str) -> Result<DateTime<Utc>, String> {
+    DateTime::parse_from_rfc3339(value)
+        .map(|d| d.with_timezone(&Utc))
+        .map_err(|_| format!("{} invalide (date RFC 3339 attendue): {}", name, value))
+}
+
+// "fr", "en", "zh-Hans", "pt-BR"...
+fn is_language_code(value: &str) -> bool {
+    let mut parts = value.split('-');
+    let primary = parts.next().unwrap_or("");
+    let subtag = parts.next();
+    parts.next().is_none()
+        && (2..=3).contains(&primary.len())
+        && primary.chars().all(|c| c.is_ascii_alphabetic())
+        && subtag.is_none_or(|t| (2..=8).contains(&t.len()) && t.chars().all(|c| c.is_ascii_alphanumeric()))
+}
+
 // --- Gestionnaire de route ---
 #[get("/search/{query}")]
-async fn search_youtube_videos(path: web::Path<String>) -> impl Responder {
+async fn search_youtube_videos(
+    req: HttpRequest,
+    path: web::Path<String>,
+    params: web::Query<SearchParams>,
+    store: web::Data<Store>,
+    cache: web::Data<SearchCache>,
+) -> impl Responder {
     let api_key = match env::var("YOUTUBE_API_KEY") {
         Ok(key) => key,
         Err(_) => {
@@ -85,21 +235,61 @@ async fn search_youtube_videos(path: web::Path<String>) -> impl Responder {
         }
     };
     let search_query = path.into_inner();
+    let api_params = match params.to_api_params() {
+        Ok(api_params) => api_params,
+        Err(e) => return HttpResponse::BadRequest().body(e),
+    };
+
+    let key = SearchCache::key(&search_query, &api_params);
+    let fetch = fetch_search_page(api_key, search_query, api_params);
+    match cache.get_or_fetch(&store, key, fetch).await {
+        Ok(entry) => {
+            // Aussi pour une réponse du cache: la recherche devient retrouvable par cet utilisateur
+            let user_id = auth::optional_user(&req, &store).await;
+            store.remember(user_id.as_deref(), entry.page.items.iter().filter_map(|v| models::Video::try_from(v).ok()).collect());
+            let (age, max_age) = cache.freshness(&entry);
+            HttpResponse::Ok()
+                .insert_header((header::CACHE_CONTROL, format!("public, max-age={}", max_age)))
+                .insert_header((header::AGE, age.to_string()))
+                .json(entry.page)
+        }
+        Err(e) => HttpResponse::build(e.status).body(e.message),
+    }
+}
+
+fn search_error(status: StatusCode, message: &str) -> SearchError {
+    SearchError {
+        status,
+        message: message.to_string(),
+    }
+}
+
+// Recherche puis récupère les détails des vidéos; appelée uniquement en cas d'absence du cache
+async fn fetch_search_page(
+    api_key: String,
+    search_query: String,
+    api_params: Vec<(&'static str, String)>,
+) -> Result<SearchPage, SearchError> {
     let client = reqwest::Client::new();
 
     // --- ÉTAPE 1: Rechercher les vidéos pour obtenir leurs IDs ---
-    let search_url = format!(
-        "https://www.googleapis.com/youtube/v3/search?part=snippet&type=video&maxResults=50&q={}&key={}",
-        search_query, api_key
-    );
+    let mut search_params = vec![
+        ("part", "snippet".to_string()),
+        ("type", "video".to_string()),
+        ("maxResults", "50".to_string()),
+        ("q", search_query),
+        ("key", api_key.clone()),
+    ];
+    search_params.extend(api_params);
+    let search_url = api_url("search", &search_params);
 
-    let search_response = match client.get(&search_url).send().await {
+    let search_response = match client.get(search_url).send().await {
         Ok(resp) => resp,
-        Err(_) => return HttpResponse::InternalServerError().body("Étape 1: Erreur de communication avec l'API YouTube."),
+        Err(_) => return Err(search_error(StatusCode::INTERNAL_SERVER_ERROR, "Étape 1: Erreur de communication avec l'API YouTube.")),
     };
 
     if !search_response.status().is_success() {
-        return HttpResponse::new(search_response.status());
+        return Err(search_error(search_response.status(), ""));
     }
 
     // Amélioration du logging pour le débogage
@@ -107,41 +297,48 @@ async fn search_youtube_videos(path: web::Path<String>) -> impl Responder {
         Ok(res) => res,
         Err(e) => {
             eprintln!("Erreur de désérialisation (Étape 1): {:?}", e);
-            return HttpResponse::InternalServerError().body("Étape 1: Erreur de désérialisation.");
+            return Err(search_error(StatusCode::INTERNAL_SERVER_ERROR, "Étape 1: Erreur de désérialisation."));
         }
     };
 
     // MODIFICATION 2: Utiliser filter_map pour ignorer les résultats sans video_id
+    let mut page = SearchPage {
+        items: Vec::new(),
+        next_page_token: search_results.next_page_token,
+        prev_page_token: search_results.prev_page_token,
+        total_results: search_results.page_info.total_results,
+    };
+
     let video_ids = search_results.items.into_iter()
         .filter_map(|item| item.id.video_id)
         .collect::<Vec<String>>()
         .join(",");
 
-    // Si après filtrage il n'y a plus aucun ID, on retourne un tableau vide.
+    // Si après filtrage il n'y a plus aucun ID, on retourne une page vide.
     if video_ids.is_empty() {
-        return HttpResponse::Ok().json(Vec::<Video>::new());
+        return Ok(page);
     }
 
     // --- ÉTAPE 2: Obtenir les détails complets (inchangée) ---
-    let details_url = format!(
-        "https://www.googleapis.com/youtube/v3/videos?part=snippet,contentDetails,statistics&id={}&key={}",
-        video_ids, api_key
+    let details_url = api_url(
+        "videos",
+        [("part", "snippet,contentDetails,statistics"), ("id", &video_ids), ("key", &api_key)],
     );
 
-    let details_response = match client.get(&details_url).send().await {
+    let details_response = match client.get(details_url).send().await {
         Ok(resp) => resp,
-        Err(_) => return HttpResponse::InternalServerError().body("Étape 2: Erreur de communication avec l'API YouTube."),
+        Err(_) => return Err(search_error(StatusCode::INTERNAL_SERVER_ERROR, "Étape 2: Erreur de communication avec l'API YouTube.")),
     };
 
     if !details_response.status().is_success() {
-        return HttpResponse::new(details_response.status());
+        return Err(search_error(details_response.status(), ""));
     }
 
     let video_details_list = match details_response.json::<VideoListResponse>().await {
         Ok(list) => list,
         Err(e) => {
             eprintln!("Erreur de désérialisation (Étape 2): {:?}", e);
-            return HttpResponse::InternalServerError().body("Étape 2: Erreur de désérialisation des détails vidéo.");
+            return Err(search_error(StatusCode::INTERNAL_SERVER_ERROR, "Étape 2: Erreur de désérialisation des détails vidéo."));
         }
     };
 
@@ -153,11 +350,77 @@ async fn search_youtube_videos(path: web::Path<String>) -> impl Responder {
             description: detail.snippet.description,
             thumbnail: detail.snippet.thumbnails.high.url,
             channel_title: detail.snippet.channel_title,
+            channel_id: detail.snippet.channel_id,
             published_at: detail.snippet.published_at,
             duration: detail.content_details.duration,
             view_count: detail.statistics.view_count,
         }
     }).collect();
 
-    HttpResponse::Ok().json(final_videos)
+    page.items = final_videos;
+
+    Ok(page)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::web;
+
+    fn api_params(query: &str) -> Result<Vec<(&'static str, String)>, String> {
+        web::Query::<SearchParams>::from_query(query).unwrap().to_api_params()
+    }
+
+    #[test]
+    fn accepts_valid_params() {
+        let cases: Vec<(&str, Vec<(&str, &str)>)> = vec![
+            ("", vec![]),
+            ("order=viewCount", vec![("order", "viewCount")]),
+            ("video_duration=long&video_definition=high", vec![("videoDuration", "long"), ("videoDefinition", "high")]),
+            ("event_type=live&safe_search=strict", vec![("eventType", "live"), ("safeSearch", "strict")]),
+            (
+                "published_after=2024-01-01T00:00:00Z&published_before=2024-02-01T12:30:00%2B02:00",
+                vec![("publishedAfter", "2024-01-01T00:00:00Z"), ("publishedBefore", "2024-02-01T10:30:00Z")],
+            ),
+            ("region_code=fr", vec![("regionCode", "FR")]),
+            ("relevance_language=fr", vec![("relevanceLanguage", "fr")]),
+            ("relevance_language=zh-Hans", vec![("relevanceLanguage", "zh-Hans")]),
+            ("relevance_language=pt-BR", vec![("relevanceLanguage", "pt-BR")]),
+            ("channel_id=UCaYhcUwRBNscFNUKTjgPFiA", vec![("channelId", "UCaYhcUwRBNscFNUKTjgPFiA")]),
+            ("page_token=CAUQAA", vec![("pageToken", "CAUQAA")]),
+        ];
+        for (query, expected) in cases {
+            let expected: Vec<(&str, String)> = expected.into_iter().map(|(k, v)| (k, v.to_string())).collect();
+            assert_eq!(api_params(query), Ok(expected), "{}", query);
+        }
+    }
+
+    #[test]
+    fn rejects_invalid_params() {
+        for query in [
+            "order=popularity",
+            "order=VIEWCOUNT",
+            "video_duration=medium-long",
+            "video_definition=4k",
+            "event_type=past",
+            "safe_search=off",
+            "published_after=2024-01-01",
+            "published_before=hier",
+            "published_after=2024-02-01T00:00:00Z&published_before=2024-01-01T00:00:00Z",
+            "published_after=2024-01-01T00:00:00Z&published_before=2024-01-01T00:00:00Z",
+            "region_code=FRA",
+            "region_code=F1",
+            "relevance_language=f",
+            "relevance_language=francais",
+            "relevance_language=fr-FR-x",
+            "channel_id=rustlang",
+            "channel_id=UCaYhcUwRBNscFNUKTjgPFi!",
+            "page_token=",
+            "page_token=abc%26key%3Dx",
+        ] {
+            assert!(api_params(query).is_err(), "{}", query);
+        }
+        let too_long = format!("page_token={}", "A".repeat(129));
+        assert!(api_params(&too_long).is_err());
+    }
 }
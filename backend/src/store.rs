@@ -0,0 +1,607 @@
+use actix_web::web;
+use serde::{Deserialize, Serialize};
+use chrono::{DateTime, Utc};
+use log::error;
+use std::collections::{HashMap, HashSet};
+use std::env;
+use std::fs;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::RwLock;
+use std::time::{Duration, Instant};
+use tokio::sync::{broadcast, Notify};
+
+use crate::channels::ChannelDetail;
+use crate::library::SearchIndex;
+use crate::models::{SavedToken, Video};
+use crate::search_cache::CachedSearch;
+use crate::transcript::Segment;
+
+// Abonnement WebSub d'une chaîne auprès du hub
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct WebSubLease {
+    pub topic: String,
+    pub requested_at: DateTime<Utc>,
+    pub expires_at: Option<DateTime<Utc>>,
+    // Désabonnement demandé au hub et pas encore vérifié
+    #[serde(default)]
+    pub unsubscribe_requested_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct StoredVideo {
+    // Numéro d'ordre de découverte, sert d'id d'événement SSE
+    #[serde(default)]
+    pub seq: u64,
+    #[serde(flatten)]
+    pub video: Video,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct UserData {
+    // Chaînes abonnées lors du dernier chargement du flux
+    #[serde(default)]
+    pub channel_ids: Vec<String>,
+    // Token OAuth conservé pour les rafraîchissements en arrière-plan
+    #[serde(default)]
+    pub token: Option<SavedToken>,
+    #[serde(default)]
+    pub refreshed_at: Option<DateTime<Utc>>,
+    // Dernier rafraîchissement en arrière-plan échoué (token révoqué, absence de refresh_token...)
+    #[serde(default)]
+    pub refresh_failed_at: Option<DateTime<Utc>>,
+    // Vidéos vues, avec la date à laquelle elles ont été marquées
+    #[serde(default)]
+    pub watched: HashMap<String, DateTime<Utc>>,
+    // File "À regarder plus tard" locale, dans l'ordre choisi par l'utilisateur
+    #[serde(default)]
+    pub queue: Vec<QueueItem>,
+    #[serde(default)]
+    pub groups: Vec<ChannelGroup>,
+    #[serde(default)]
+    pub filters: Vec<FilterRule>,
+    // Chaînes masquées du flux, indexées par channel_id
+    #[serde(default)]
+    pub muted_channels: HashMap<String, ChannelMute>,
+    // Vidéos hors flux consultées par l'utilisateur (recherche, chaînes, playlists)
+    #[serde(default)]
+    pub library_ids: HashSet<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ChannelMute {
+    pub muted_at: DateTime<Utc>,
+    // Fin de la mise en sourdine, indéfinie si absente
+    #[serde(default)]
+    pub until: Option<DateTime<Utc>>,
+}
+
+impl ChannelMute {
+    pub fn is_active(&self, now: DateTime<Utc>) -> bool {
+        self.until.is_none_or(|until| until > now)
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum RuleAction {
+    Mute,
+    Allow,
+}
+
+// Règle de filtrage du flux: tous les critères renseignés doivent correspondre
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct FilterRule {
+    #[serde(default)]
+    pub id: u64,
+    pub action: RuleAction,
+    #[serde(default)]
+    pub title_regex: Option<String>,
+    #[serde(default)]
+    pub description_keyword: Option<String>,
+    #[serde(default)]
+    pub channel_id: Option<String>,
+    // Bornes de durée en secondes, incluses
+    #[serde(default)]
+    pub min_duration: Option<u64>,
+    #[serde(default)]
+    pub max_duration: Option<u64>,
+}
+
+// Groupe de chaînes défini par l'utilisateur ("Rust", "Musique"...)
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ChannelGroup {
+    pub name: String,
+    #[serde(default)]
+    pub channel_ids: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct QueueItem {
+    pub video: Video,
+    pub added_at: DateTime<Utc>,
+}
+
+// Planification du rafraîchissement des uploads d'une chaîne
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct ChannelSchedule {
+    #[serde(default)]
+    pub uploads_playlist: Option<String>,
+    #[serde(default)]
+    pub next_poll_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub last_polled_at: Option<DateTime<Utc>>,
+    // L'API n'a renvoyé aucune playlist d'uploads (chaîne supprimée ou suspendue)
+    #[serde(default)]
+    pub uploads_missing_at: Option<DateTime<Utc>>,
+}
+
+// Transcription d'une vidéo; aucun segment signifie qu'aucune piste n'était disponible
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct StoredTranscript {
+    pub language: Option<String>,
+    pub segments: Vec<Segment>,
+    pub fetched_at: DateTime<Utc>,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+pub struct StoreData {
+    // Vidéos connues, indexées par video_id
+    #[serde(default)]
+    pub videos: HashMap<String, StoredVideo>,
+    #[serde(default)]
+    pub last_seq: u64,
+    // Baux WebSub, indexés par channel_id
+    #[serde(default)]
+    pub websub: HashMap<String, WebSubLease>,
+    // Données propres à chaque utilisateur, indexées par l'id de sa chaîne YouTube
+    #[serde(default)]
+    pub users: HashMap<String, UserData>,
+    // Planification du rafraîchissement en arrière-plan, indexée par channel_id
+    #[serde(default)]
+    pub channels: HashMap<String, ChannelSchedule>,
+    // Vidéos hors flux déjà récupérées (recherche, uploads d'une chaîne), indexées par video_id
+    #[serde(default)]
+    pub library: HashMap<String, Video>,
+    // Détails des chaînes consultées via /channels/{id}, indexés par channel_id
+    #[serde(default)]
+    pub channel_details: HashMap<String, ChannelDetail>,
+    // Transcriptions récupérées, indexées par video_id
+    #[serde(default)]
+    pub transcripts: HashMap<String, StoredTranscript>,
+    // Résultats de /search/{query}, conservés seulement si SEARCH_CACHE_PERSIST est actif
+    #[serde(default)]
+    pub search_cache: HashMap<String, CachedSearch>,
+    // Date de la dernière vidéo connue de chaque chaîne, conservée après la purge des vidéos
+    #[serde(default)]
+    pub last_uploads: HashMap<String, DateTime<Utc>>,
+}
+
+impl StoreData {
+    // Retourne l'entrée créée si la vidéo n'était pas encore connue
+    pub fn upsert_video(&mut self, video: Video) -> Option<StoredVideo> {
+        self.record_upload(&video);
+        if let Some(existing) = self.videos.get_mut(&video.video_id) {
+            merge_video(&mut existing.video, video);
+            return None;
+        }
+
+        self.last_seq += 1;
+        let entry = StoredVideo {
+            seq: self.last_seq,
+            video,
+        };
+        self.videos.insert(entry.video.video_id.clone(), entry.clone());
+        Some(entry)
+    }
+
+    pub fn channel_videos(&self, channel_ids: &[String]) -> Vec<Video> {
+        self.videos
+            .values()
+            .filter(|v| channel_ids.contains(&v.video.channel_id))
+            .map(|v| v.video.clone())
+            .collect()
+    }
+
+    // Vidéos découvertes après `seq` pour les chaînes données, dans l'ordre de découverte
+    pub fn videos_since(&self, seq: u64, channel_ids: &[String]) -> Vec<StoredVideo> {
+        let mut entries: Vec<StoredVideo> = self.videos
+            .values()
+            .filter(|v| v.seq > seq && channel_ids.contains(&v.video.channel_id))
+            .cloned()
+            .collect();
+        entries.sort_by_key(|v| v.seq);
+        entries
+    }
+
+    pub fn find_video(&self, video_id: &str) -> Option<&Video> {
+        self.videos
+            .get(video_id)
+            .map(|v| &v.video)
+            .or_else(|| self.library.get(video_id))
+    }
+
+    pub fn latest_upload(&self, channel_id: &str) -> Option<DateTime<Utc>> {
+        self.last_uploads.get(channel_id).copied()
+    }
+
+    fn record_upload(&mut self, video: &Video) {
+        let last = self.last_uploads.entry(video.channel_id.clone()).or_insert(video.published_at);
+        *last = (*last).max(video.published_at);
+    }
+
+    pub fn group(&self, user_id: &str, name: &str) -> Option<&ChannelGroup> {
+        self.users
+            .get(user_id)
+            .and_then(|u| u.groups.iter().find(|g| g.name == name))
+    }
+
+    pub fn muted_channels(&self, user_id: &str, now: DateTime<Utc>) -> Vec<String> {
+        self.users
+            .get(user_id)
+            .map(|u| {
+                u.muted_channels
+                    .iter()
+                    .filter(|(_, mute)| mute.is_active(now))
+                    .map(|(channel_id, _)| channel_id.clone())
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    pub fn user_channels(&self, user_id: &str) -> Vec<String> {
+        self.users
+            .get(user_id)
+            .map(|u| u.channel_ids.clone())
+            .unwrap_or_default()
+    }
+}
+
+// Met à jour une vidéo connue sans perdre les informations absentes de la nouvelle version:
+// playlistItems ne donne pas la durée et les notifications WebSub pas la description
+fn merge_video(existing: &mut Video, mut incoming: Video) {
+    if incoming.duration_seconds.is_none() {
+        incoming.duration_seconds = existing.duration_seconds;
+    }
+    if incoming.description.is_empty() {
+        incoming.description = std::mem::take(&mut existing.description);
+    }
+    *existing = incoming;
+}
+
+// Délai de regroupement des écritures avant sauvegarde du fichier
+const PERSIST_DELAY: Duration = Duration::from_secs(1);
+// Un access_token Google expire au bout d'une heure
+const SESSION_TTL: Duration = Duration::from_secs(3600);
+const MAX_SESSIONS: usize = 1000;
+
+struct Session {
+    user_id: String,
+    expires_at: Instant,
+    // Scopes du token présenté, connus après la connexion ou un appel à tokeninfo
+    scopes: Option<Vec<String>>,
+}
+
+// Stockage local partagé entre les handlers, sauvegardé dans un fichier JSON
+pub struct Store {
+    path: String,
+    data: RwLock<StoreData>,
+    // Modifié depuis la dernière sauvegarde; la tâche persist_loop est réveillée par `changed`
+    dirty: AtomicBool,
+    changed: Notify,
+    // Association access_token -> id utilisateur, conservée en mémoire uniquement
+    sessions: RwLock<HashMap<String, Session>>,
+    events: broadcast::Sender<StoredVideo>,
+    // Index plein texte reconstruit au démarrage puis tenu à jour à chaque écriture de vidéo
+    index: RwLock<SearchIndex>,
+}
+
+impl Store {
+    pub fn load() -> Store {
+        let path = env::var("STORE_PATH").unwrap_or_else(|_| "store.json".to_string());
+        let data = match fs::read_to_string(&path) {
+            Ok(content) => match serde_json::from_str(&content) {
+                Ok(data) => data,
+                Err(e) => {
+                    error!("Fichier {} corrompu, démarrage avec un stockage vide: {}", path, e);
+                    StoreData::default()
+                }
+            },
+            Err(_) => StoreData::default(),
+        };
+        Store::new(path, data)
+    }
+
+    // Stockage vide jamais sauvegardé, pour les tests
+    #[cfg(test)]
+    pub fn in_memory() -> Store {
+        Store::new(String::new(), StoreData::default())
+    }
+
+    fn new(path: String, mut data: StoreData) -> Store {
+        // Fichiers antérieurs à last_uploads
+        for entry in data.videos.values() {
+            let last = data.last_uploads.entry(entry.video.channel_id.clone()).or_insert(entry.video.published_at);
+            *last = (*last).max(entry.video.published_at);
+        }
+
+        let (events, _) = broadcast::channel(256);
+        let index = SearchIndex::build(&data);
+
+        Store {
+            path,
+            data: RwLock::new(data),
+            dirty: AtomicBool::new(false),
+            changed: Notify::new(),
+            sessions: RwLock::new(HashMap::new()),
+            events,
+            index: RwLock::new(index),
+        }
+    }
+
+    pub fn read<R>(&self, f: impl FnOnce(&StoreData) -> R) -> R {
+        let data = self.data.read().unwrap();
+        f(&data)
+    }
+
+    // Applique la modification; la sauvegarde est faite plus tard par persist_loop
+    pub fn write<R>(&self, f: impl FnOnce(&mut StoreData) -> R) -> R {
+        let result = f(&mut self.data.write().unwrap());
+        self.dirty.store(true, Ordering::Release);
+        self.changed.notify_one();
+        result
+    }
+
+    // Tâche de fond: sauvegarde le fichier après une rafale d'écritures.
+    // main appelle flush une dernière fois à l'arrêt
+    pub async fn persist_loop(store: web::Data<Store>) {
+        loop {
+            store.changed.notified().await;
+            tokio::time::sleep(PERSIST_DELAY).await;
+            store.flush().await;
+        }
+    }
+
+    // Sérialise sous verrou de lecture, puis écrit hors verrou dans un thread bloquant.
+    // Le fichier temporaire renommé évite un store.json tronqué en cas d'arrêt brutal
+    pub async fn flush(&self) {
+        if !self.dirty.swap(false, Ordering::AcqRel) {
+            return;
+        }
+
+        let json = match serde_json::to_vec(&*self.data.read().unwrap()) {
+            Ok(json) => json,
+            Err(e) => {
+                error!("Erreur de sérialisation du stockage: {}", e);
+                return;
+            }
+        };
+
+        let path = self.path.clone();
+        let written = tokio::task::spawn_blocking(move || {
+            let tmp = format!("{}.tmp", path);
+            fs::write(&tmp, json).and_then(|_| fs::rename(&tmp, &path))
+        })
+        .await;
+        match written {
+            Ok(Ok(())) => {}
+            Ok(Err(e)) => {
+                error!("Impossible d'écrire {}: {}", self.path, e);
+                self.dirty.store(true, Ordering::Release);
+            }
+            Err(e) => error!("Tâche de sauvegarde interrompue: {}", e),
+        }
+    }
+
+    // Ajoute les vidéos au stockage et notifie les flux SSE des nouvelles
+    pub fn ingest(&self, videos: Vec<Video>) -> usize {
+        let (new_entries, stored): (Vec<StoredVideo>, Vec<Video>) = self.write(|data| {
+            let mut new_entries = Vec::new();
+            let mut stored = Vec::new();
+            for video in videos {
+                let video_id = video.video_id.clone();
+                // Une vidéo déjà conservée hors flux garde ses informations en y entrant
+                let video = match data.library.remove(&video_id) {
+                    Some(mut previous) => {
+                        merge_video(&mut previous, video);
+                        previous
+                    }
+                    None => video,
+                };
+                new_entries.extend(data.upsert_video(video));
+                stored.extend(data.videos.get(&video_id).map(|entry| entry.video.clone()));
+            }
+            (new_entries, stored)
+        });
+        self.reindex(&stored);
+
+        let count = new_entries.len();
+        for entry in new_entries {
+            // Aucun abonné n'est pas une erreur
+            let _ = self.events.send(entry);
+        }
+        count
+    }
+
+    // Conserve des vidéos hors flux pour la recherche locale; `user_id` est l'utilisateur
+    // qui les a consultées, seul à les retrouver via /library/search
+    pub fn remember(&self, user_id: Option<&str>, videos: Vec<Video>) {
+        if videos.is_empty() {
+            return;
+        }
+
+        let stored: Vec<Video> = self.write(|data| {
+            if let Some(user_id) = user_id {
+                let user = data.users.entry(user_id.to_string()).or_default();
+                user.library_ids.extend(videos.iter().map(|video| video.video_id.clone()));
+            }
+            videos
+                .into_iter()
+                .map(|video| {
+                    let existing = match data.videos.get_mut(&video.video_id) {
+                        Some(entry) => Some(&mut entry.video),
+                        None => data.library.get_mut(&video.video_id),
+                    };
+                    match existing {
+                        Some(existing) => {
+                            merge_video(existing, video);
+                            existing.clone()
+                        }
+                        None => {
+                            data.library.insert(video.video_id.clone(), video.clone());
+                            video
+                        }
+                    }
+                })
+                .collect()
+        });
+        self.reindex(&stored);
+    }
+
+    // L'index reçoit la version fusionnée, pas la vidéo reçue qui peut être incomplète
+    fn reindex(&self, videos: &[Video]) {
+        let mut index = self.index.write().unwrap();
+        for video in videos {
+            index.add(video);
+        }
+    }
+
+    pub fn remove_videos(&self, video_ids: &[String]) {
+        {
+            let mut index = self.index.write().unwrap();
+            for video_id in video_ids {
+                index.remove(video_id);
+            }
+        }
+
+        self.write(|data| {
+            for video_id in video_ids {
+                data.videos.remove(video_id);
+                data.library.remove(video_id);
+                data.transcripts.remove(video_id);
+            }
+            for user in data.users.values_mut() {
+                user.library_ids.retain(|id| !video_ids.contains(id));
+            }
+        });
+    }
+
+    // Enregistre la transcription d'une vidéo et l'ajoute à l'index plein texte
+    pub fn save_transcript(&self, video_id: &str, transcript: StoredTranscript) {
+        self.save_transcripts(vec![(video_id.to_string(), transcript)]);
+    }
+
+    pub fn save_transcripts(&self, transcripts: Vec<(String, StoredTranscript)>) {
+        if transcripts.is_empty() {
+            return;
+        }
+
+        {
+            let mut index = self.index.write().unwrap();
+            for (video_id, transcript) in &transcripts {
+                index.set_transcript(video_id, &transcript.segments);
+            }
+        }
+        self.write(|data| data.transcripts.extend(transcripts));
+    }
+
+    pub fn search(&self, query: &str) -> Vec<(String, f64)> {
+        self.index.read().unwrap().search(query)
+    }
+
+    pub fn subscribe_events(&self) -> broadcast::Receiver<StoredVideo> {
+        self.events.subscribe()
+    }
+
+    pub fn session_user(&self, access_token: &str) -> Option<String> {
+        self.sessions
+            .read()
+            .unwrap()
+            .get(access_token)
+            .filter(|session| session.expires_at > Instant::now())
+            .map(|session| session.user_id.clone())
+    }
+
+    // Les sessions expirées sont purgées quand la table est pleine, puis la plus ancienne si besoin
+    pub fn remember_session(&self, access_token: &str, user_id: &str) {
+        let now = Instant::now();
+        let mut sessions = self.sessions.write().unwrap();
+        if sessions.len() >= MAX_SESSIONS {
+            sessions.retain(|_, session| session.expires_at > now);
+        }
+        if sessions.len() >= MAX_SESSIONS {
+            let oldest = sessions.iter().min_by_key(|(_, session)| session.expires_at).map(|(token, _)| token.clone());
+            if let Some(token) = oldest {
+                sessions.remove(&token);
+            }
+        }
+        sessions.insert(
+            access_token.to_string(),
+            Session {
+                user_id: user_id.to_string(),
+                expires_at: now + SESSION_TTL,
+                scopes: None,
+            },
+        );
+    }
+
+    pub fn session_scopes(&self, access_token: &str) -> Option<Vec<String>> {
+        self.sessions
+            .read()
+            .unwrap()
+            .get(access_token)
+            .filter(|session| session.expires_at > Instant::now())
+            .and_then(|session| session.scopes.clone())
+    }
+
+    // Sans effet si la session n'est pas connue: current_user doit être appelé avant
+    pub fn remember_scopes(&self, access_token: &str, scopes: Vec<String>) {
+        if let Some(session) = self.sessions.write().unwrap().get_mut(access_token) {
+            session.scopes = Some(scopes);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn video(description: &str, duration_seconds: Option<u64>) -> Video {
+        Video {
+            url: "https://www.youtube.com/watch?v=dQw4w9WgXcQ".to_string(),
+            video_id: "dQw4w9WgXcQ".to_string(),
+            published_at: Utc::now(),
+            title: "Titre".to_string(),
+            thumbnail: String::new(),
+            channel_title: "Chaîne".to_string(),
+            channel_id: "UCaYhcUwRBNscFNUKTjgPFiA".to_string(),
+            description: description.to_string(),
+            duration_seconds,
+        }
+    }
+
+    #[test]
+    fn upsert_keeps_known_duration_and_description() {
+        let mut data = StoreData::default();
+        assert!(data.upsert_video(video("Description complète", Some(212))).is_some());
+
+        let mut update = video("", None);
+        update.title = "Nouveau titre".to_string();
+        assert!(data.upsert_video(update).is_none());
+
+        let stored = &data.videos["dQw4w9WgXcQ"].video;
+        assert_eq!(stored.title, "Nouveau titre");
+        assert_eq!(stored.description, "Description complète");
+        assert_eq!(stored.duration_seconds, Some(212));
+    }
+
+    #[test]
+    fn upsert_replaces_known_values_with_new_ones() {
+        let mut data = StoreData::default();
+        data.upsert_video(video("Ancienne", Some(10)));
+        data.upsert_video(video("Nouvelle", Some(20)));
+
+        let stored = &data.videos["dQw4w9WgXcQ"].video;
+        assert_eq!(stored.description, "Nouvelle");
+        assert_eq!(stored.duration_seconds, Some(20));
+    }
+}
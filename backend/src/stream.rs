@@ -0,0 +1,116 @@
+use actix_web::{get, web, HttpRequest, HttpResponse};
+use futures::stream;
+use log::{info, warn};
+use std::collections::{HashMap, VecDeque};
+use std::time::Duration;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::broadcast::Receiver;
+
+use crate::auth;
+use crate::store::{Store, StoredVideo};
+
+// Intervalle des commentaires envoyés pour garder la connexion ouverte
+const KEEP_ALIVE: Duration = Duration::from_secs(15);
+
+struct StreamState {
+    store: web::Data<Store>,
+    user_id: String,
+    events: Receiver<StoredVideo>,
+    pending: VecDeque<StoredVideo>,
+    last_seq: u64,
+}
+
+impl StreamState {
+    // Les chaînes sont relues à chaque événement pour suivre les changements d'abonnements
+    fn follows(&self, entry: &StoredVideo) -> bool {
+        self.store
+            .read(|data| data.user_channels(&self.user_id))
+            .contains(&entry.video.channel_id)
+    }
+
+    fn replay(&mut self) {
+        let channel_ids = self.store.read(|data| data.user_channels(&self.user_id));
+        let missed = self.store.read(|data| data.videos_since(self.last_seq, &channel_ids));
+        self.pending.extend(missed);
+    }
+}
+
+fn format_event(entry: &StoredVideo) -> web::Bytes {
+    let data = serde_json::to_string(&entry.video).unwrap_or_default();
+    web::Bytes::from(format!("id: {}\nevent: video\ndata: {}\n\n", entry.seq, data))
+}
+
+fn last_event_id(req: &HttpRequest) -> Option<u64> {
+    req.headers()
+        .get("last-event-id")
+        .and_then(|v| v.to_str().ok())
+        .map(|v| v.to_string())
+        .or_else(|| {
+            web::Query::<HashMap<String, String>>::from_query(req.query_string())
+                .ok()
+                .and_then(|q| q.get("last_event_id").cloned())
+        })
+        .and_then(|v| v.trim().parse().ok())
+}
+
+#[get("/subscriptions/videos/stream")]
+pub async fn subscriptions_videos_stream(req: HttpRequest, store: web::Data<Store>) -> HttpResponse {
+    let access_token = match auth::stream_access_token(&req) {
+        Ok(token) => token,
+        Err(response) => return response,
+    };
+    let user_id = match auth::current_user(&store, &access_token).await {
+        Ok(id) => id,
+        Err(response) => return response,
+    };
+
+    // Abonnement avant la relecture pour ne perdre aucune vidéo entre les deux
+    let events = store.subscribe_events();
+    let resume_from = last_event_id(&req);
+
+    let mut state = StreamState {
+        store: store.clone(),
+        user_id,
+        events,
+        pending: VecDeque::new(),
+        last_seq: store.read(|data| data.last_seq),
+    };
+
+    if let Some(seq) = resume_from {
+        info!("Reprise du flux SSE de {} après l'événement {}", state.user_id, seq);
+        state.last_seq = seq;
+        state.replay();
+    }
+
+    let body = stream::unfold(state, |mut state| async move {
+        loop {
+            if let Some(entry) = state.pending.pop_front() {
+                if entry.seq <= state.last_seq {
+                    continue;
+                }
+                state.last_seq = entry.seq;
+                let event = format_event(&entry);
+                return Some((Ok::<_, actix_web::Error>(event), state));
+            }
+
+            match tokio::time::timeout(KEEP_ALIVE, state.events.recv()).await {
+                Err(_) => return Some((Ok(web::Bytes::from_static(b": keep-alive\n\n")), state)),
+                Ok(Ok(entry)) => {
+                    if entry.seq > state.last_seq && state.follows(&entry) {
+                        state.pending.push_back(entry);
+                    }
+                }
+                Ok(Err(RecvError::Lagged(skipped))) => {
+                    warn!("Flux SSE de {} en retard de {} événements, relecture du stockage", state.user_id, skipped);
+                    state.replay();
+                }
+                Ok(Err(RecvError::Closed)) => return None,
+            }
+        }
+    });
+
+    HttpResponse::Ok()
+        .content_type("text/event-stream")
+        .insert_header(("Cache-Control", "no-cache"))
+        .streaming(body)
+}
@@ -0,0 +1,317 @@
+use actix_web::http::StatusCode;
+use actix_web::{delete, post, web, HttpRequest, HttpResponse};
+use log::info;
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use crate::auth;
+use crate::channels::is_channel_id;
+use crate::opml;
+use crate::store::Store;
+use crate::subscriptions::fetch_subscription_ids;
+use crate::websub;
+use crate::youtube_api::{api_url, send_json};
+
+#[derive(Deserialize, Debug)]
+pub struct SyncQuery {
+    // Calcule les changements sans les appliquer
+    #[serde(default)]
+    dry_run: bool,
+    // Se désabonne aussi des chaînes absentes du fichier; sinon seuls les ajouts sont appliqués
+    #[serde(default)]
+    prune: bool,
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+pub struct SyncReport {
+    pub dry_run: bool,
+    pub subscribed: Vec<String>,
+    pub unsubscribed: Vec<String>,
+    // Abonnements absents du fichier conservés faute de prune
+    pub kept: Vec<String>,
+    pub unchanged: usize,
+    pub failed: Vec<String>,
+    // Changements non tentés après un dépassement de quota ou un refus de l'API
+    pub skipped: Vec<String>,
+}
+
+// Différence entre les chaînes du fichier et les abonnements actuels
+#[derive(Debug, PartialEq)]
+struct SyncPlan {
+    to_add: Vec<String>,
+    // (channel_id, subscription_id)
+    to_remove: Vec<(String, String)>,
+    kept: Vec<String>,
+    unchanged: usize,
+}
+
+impl SyncPlan {
+    fn new(wanted: &[String], current: &[(String, String)], prune: bool) -> SyncPlan {
+        let to_add: Vec<String> = wanted
+            .iter()
+            .filter(|id| !current.iter().any(|(channel_id, _)| channel_id == *id))
+            .cloned()
+            .collect();
+        let extra: Vec<(String, String)> = current.iter().filter(|(channel_id, _)| !wanted.contains(channel_id)).cloned().collect();
+        let (to_remove, kept) = if prune { (extra, Vec::new()) } else { (Vec::new(), extra) };
+
+        SyncPlan {
+            unchanged: wanted.len() - to_add.len(),
+            to_add,
+            to_remove,
+            kept: kept.into_iter().map(|(channel_id, _)| channel_id).collect(),
+        }
+    }
+
+    fn report(&self, dry_run: bool) -> SyncReport {
+        SyncReport {
+            dry_run,
+            subscribed: Vec::new(),
+            unsubscribed: Vec::new(),
+            kept: self.kept.clone(),
+            unchanged: self.unchanged,
+            failed: Vec::new(),
+            skipped: Vec::new(),
+        }
+    }
+
+    // Rapport d'une simulation: tous les changements prévus sont listés comme appliqués
+    fn dry_run_report(&self) -> SyncReport {
+        SyncReport {
+            subscribed: self.to_add.clone(),
+            unsubscribed: self.to_remove.iter().map(|(channel_id, _)| channel_id.clone()).collect(),
+            ..self.report(true)
+        }
+    }
+}
+
+#[post("/subscriptions/sync")]
+pub async fn sync_from_opml(req: HttpRequest, query: web::Query<SyncQuery>, body: String, store: web::Data<Store>) -> HttpResponse {
+    let access_token = match auth::require_scope(&req, &store, auth::WRITE_SCOPE).await {
+        Ok(token) => token,
+        Err(response) => return response,
+    };
+    let wanted = match opml::channel_ids(&body) {
+        Ok(ids) => ids,
+        Err(e) => return HttpResponse::BadRequest().body(format!("OPML invalide: {}", e)),
+    };
+    // Un fichier vide ou sans chaîne YouTube désabonnerait le compte de tout
+    if query.prune && wanted.is_empty() {
+        return HttpResponse::BadRequest().body("Aucune chaîne YouTube dans le fichier, prune refusé");
+    }
+
+    let client = Client::new();
+    let current = match fetch_subscription_ids(&client, &access_token).await {
+        Ok(ids) => ids,
+        Err(e) => return HttpResponse::InternalServerError().body(e),
+    };
+
+    let plan = SyncPlan::new(&wanted, &current, query.prune);
+    if query.dry_run {
+        return HttpResponse::Ok().json(plan.dry_run_report());
+    }
+    let mut report = plan.report(false);
+
+    // Appliqué une chaîne à la fois: chaque opération coûte 50 unités de quota
+    let mut to_add = plan.to_add.into_iter();
+    for channel_id in to_add.by_ref() {
+        match insert_subscription(&client, &access_token, &channel_id).await {
+            Ok(()) => report.subscribed.push(channel_id),
+            Err(response) if stops_sync(&response) => {
+                report.failed.push(channel_id);
+                break;
+            }
+            Err(_) => report.failed.push(channel_id),
+        }
+    }
+    report.skipped.extend(to_add);
+
+    let mut to_remove = plan.to_remove.into_iter();
+    if report.skipped.is_empty() {
+        for (channel_id, subscription_id) in to_remove.by_ref() {
+            match delete_subscription(&client, &access_token, &subscription_id).await {
+                Ok(()) => report.unsubscribed.push(channel_id),
+                Err(response) if stops_sync(&response) => {
+                    report.failed.push(channel_id);
+                    break;
+                }
+                Err(_) => report.failed.push(channel_id),
+            }
+        }
+    }
+    report.skipped.extend(to_remove.map(|(channel_id, _)| channel_id));
+
+    info!(
+        "Synchronisation OPML: {} abonnements, {} désabonnements, {} échecs, {} non tentés",
+        report.subscribed.len(),
+        report.unsubscribed.len(),
+        report.failed.len(),
+        report.skipped.len()
+    );
+    if let Ok(user_id) = auth::current_user(&store, &access_token).await {
+        record_changes(&store, &user_id, &report.subscribed, &report.unsubscribed);
+    }
+    tokio::spawn(websub::ensure_subscribed(store.clone(), report.subscribed.clone()));
+    tokio::spawn(websub::release_unfollowed(store.clone(), report.unsubscribed.clone()));
+
+    HttpResponse::Ok().json(report)
+}
+
+// quotaExceeded et les refus d'autorisation répondent 403: les appels suivants échoueraient aussi
+fn stops_sync(response: &HttpResponse) -> bool {
+    matches!(response.status(), StatusCode::FORBIDDEN | StatusCode::UNAUTHORIZED | StatusCode::TOO_MANY_REQUESTS)
+}
+
+#[post("/subscriptions/{channel_id}")]
+pub async fn subscribe(req: HttpRequest, path: web::Path<String>, store: web::Data<Store>) -> HttpResponse {
+    let access_token = match auth::require_scope(&req, &store, auth::WRITE_SCOPE).await {
+        Ok(token) => token,
+        Err(response) => return response,
+    };
+    let channel_id = path.into_inner();
+    if !is_channel_id(&channel_id) {
+        return HttpResponse::BadRequest().body(format!("channel_id invalide: {}", channel_id));
+    }
+
+    // Un abonnement existant renvoie 409 (subscriptionDuplicate)
+    if let Err(response) = insert_subscription(&Client::new(), &access_token, &channel_id).await {
+        return response;
+    }
+
+    if let Ok(user_id) = auth::current_user(&store, &access_token).await {
+        record_changes(&store, &user_id, std::slice::from_ref(&channel_id), &[]);
+    }
+    tokio::spawn(websub::ensure_subscribed(store.clone(), vec![channel_id]));
+    HttpResponse::NoContent().finish()
+}
+
+#[delete("/subscriptions/{channel_id}")]
+pub async fn unsubscribe(req: HttpRequest, path: web::Path<String>, store: web::Data<Store>) -> HttpResponse {
+    let access_token = match auth::require_scope(&req, &store, auth::WRITE_SCOPE).await {
+        Ok(token) => token,
+        Err(response) => return response,
+    };
+    let channel_id = path.into_inner();
+    if !is_channel_id(&channel_id) {
+        return HttpResponse::BadRequest().body(format!("channel_id invalide: {}", channel_id));
+    }
+    let client = Client::new();
+
+    // subscriptions.delete attend l'id de l'abonnement, retrouvé à partir de la chaîne
+    let url = api_url("subscriptions", [("part", "id"), ("mine", "true"), ("forChannelId", channel_id.as_str())]);
+    let body = match send_json(client.get(url).bearer_auth(&access_token), "/subscriptions?forChannelId").await {
+        Ok(body) => body,
+        Err(response) => return response,
+    };
+    let subscription_id = match body["items"][0]["id"].as_str() {
+        Some(id) => id.to_string(),
+        None => return HttpResponse::NotFound().body("Aucun abonnement à cette chaîne"),
+    };
+
+    if let Err(response) = delete_subscription(&client, &access_token, &subscription_id).await {
+        return response;
+    }
+
+    if let Ok(user_id) = auth::current_user(&store, &access_token).await {
+        record_changes(&store, &user_id, &[], std::slice::from_ref(&channel_id));
+    }
+    tokio::spawn(websub::release_unfollowed(store.clone(), vec![channel_id]));
+    HttpResponse::NoContent().finish()
+}
+
+async fn insert_subscription(client: &Client, access_token: &str, channel_id: &str) -> Result<(), HttpResponse> {
+    let resource = json!({
+        "snippet": { "resourceId": { "kind": "youtube#channel", "channelId": channel_id } },
+    });
+    let url = api_url("subscriptions", [("part", "snippet")]);
+    send_json(client.post(url).bearer_auth(access_token).json(&resource), "subscriptions.insert").await?;
+    info!("Abonnement à la chaîne {}", channel_id);
+    Ok(())
+}
+
+async fn delete_subscription(client: &Client, access_token: &str, subscription_id: &str) -> Result<(), HttpResponse> {
+    let url = api_url("subscriptions", [("id", subscription_id)]);
+    send_json(client.delete(url).bearer_auth(access_token), "subscriptions.delete").await?;
+    info!("Abonnement {} supprimé", subscription_id);
+    Ok(())
+}
+
+// Tient à jour la liste locale des chaînes sans attendre le prochain chargement du flux
+fn record_changes(store: &Store, user_id: &str, subscribed: &[String], unsubscribed: &[String]) {
+    if subscribed.is_empty() && unsubscribed.is_empty() {
+        return;
+    }
+    store.write(|data| {
+        let user = data.users.entry(user_id.to_string()).or_default();
+        user.channel_ids.retain(|id| !unsubscribed.contains(id));
+        for channel_id in subscribed {
+            if !user.channel_ids.contains(channel_id) {
+                user.channel_ids.push(channel_id.clone());
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ids(values: &[&str]) -> Vec<String> {
+        values.iter().map(|v| v.to_string()).collect()
+    }
+
+    fn current() -> Vec<(String, String)> {
+        vec![
+            ("UC_garde".to_string(), "sub-garde".to_string()),
+            ("UC_retire".to_string(), "sub-retire".to_string()),
+        ]
+    }
+
+    #[test]
+    fn plans_additions_and_keeps_extra_without_prune() {
+        let plan = SyncPlan::new(&ids(&["UC_garde", "UC_nouveau"]), &current(), false);
+        assert_eq!(
+            plan,
+            SyncPlan {
+                to_add: ids(&["UC_nouveau"]),
+                to_remove: Vec::new(),
+                kept: ids(&["UC_retire"]),
+                unchanged: 1,
+            }
+        );
+    }
+
+    #[test]
+    fn plans_removals_with_prune() {
+        let plan = SyncPlan::new(&ids(&["UC_garde", "UC_nouveau"]), &current(), true);
+        assert_eq!(plan.to_add, ids(&["UC_nouveau"]));
+        assert_eq!(plan.to_remove, vec![("UC_retire".to_string(), "sub-retire".to_string())]);
+        assert!(plan.kept.is_empty());
+        assert_eq!(plan.unchanged, 1);
+    }
+
+    #[test]
+    fn plans_nothing_when_in_sync() {
+        let plan = SyncPlan::new(&ids(&["UC_retire", "UC_garde"]), &current(), true);
+        assert!(plan.to_add.is_empty() && plan.to_remove.is_empty() && plan.kept.is_empty());
+        assert_eq!(plan.unchanged, 2);
+    }
+
+    #[test]
+    fn dry_run_reports_planned_changes() {
+        let plan = SyncPlan::new(&ids(&["UC_garde", "UC_nouveau"]), &current(), true);
+        assert_eq!(
+            plan.dry_run_report(),
+            SyncReport {
+                dry_run: true,
+                subscribed: ids(&["UC_nouveau"]),
+                unsubscribed: ids(&["UC_retire"]),
+                kept: Vec::new(),
+                unchanged: 1,
+                failed: Vec::new(),
+                skipped: Vec::new(),
+            }
+        );
+    }
+}
@@ -2,16 +2,23 @@ use actix_web::{get, web, HttpResponse, HttpRequest};
 use oauth2::{AuthorizationCode, CsrfToken, Scope, TokenResponse};
 use oauth2::reqwest::async_http_client;
 use oauth2::basic::BasicClient;
-use reqwest::{Client, StatusCode};
+use reqwest::{Client, StatusCode, Url};
 use serde_json::Value;
+use std::cmp::Reverse;
 use std::env;
 use chrono::{DateTime, Utc};
 use log::{info, error, warn};
 use futures::future::join_all;
 use serde::{Deserialize, Serialize};
 
-use crate::auth::oauth_client;
-use crate::models::{SavedToken, Video};
+use crate::auth::{self, oauth_client};
+use crate::filters;
+use crate::models::{SavedToken, Subscription, Video};
+use crate::scheduler;
+use crate::store::Store;
+use crate::watched;
+use crate::websub;
+use crate::youtube_api::{api_url, send_json};
 
 #[derive(Serialize, Deserialize, Debug)]
 pub struct AuthCallbackQuery {
@@ -19,20 +26,56 @@ pub struct AuthCallbackQuery {
     state: String,
 }
 
+#[derive(Deserialize, Debug)]
+pub struct LoginQuery {
+    // Demande en plus le scope d'écriture, nécessaire pour modifier les playlists
+    #[serde(default)]
+    write: bool,
+    // Demande le scope de téléchargement des sous-titres (pistes des vidéos de l'utilisateur)
+    #[serde(default)]
+    captions: bool,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct SubscriptionsQuery {
+    // "alphabetical" (par défaut), "relevance", "subscribed" ou "last_upload".
+    // last_upload ne connaît que les chaînes déjà chargées dans le flux: les autres sont classées en dernier
+    order: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct FeedQuery {
+    // Masque les vidéos déjà marquées comme vues
+    #[serde(default)]
+    unwatched_only: bool,
+    // Restreint le flux aux chaînes d'un groupe
+    group: Option<String>,
+}
+
 #[get("/")]
 pub async fn index() -> HttpResponse {
     HttpResponse::Ok().body("<html>Bienvenue 👋<br><a href=\"/login\">Se connecter avec Google</a></html>")
 }
 
 #[get("/login")]
-pub async fn login() -> HttpResponse {
+pub async fn login(query: web::Query<LoginQuery>) -> HttpResponse {
     let client = oauth_client();
-    let (auth_url, _csrf_token) = client
+    let mut request = client
         .authorize_url(CsrfToken::new_random)
-        .add_scope(Scope::new("https://www.googleapis.com/auth/youtube.readonly".to_string()))
+        .add_scope(Scope::new(auth::READ_SCOPE.to_string()))
         .add_extra_param("access_type", "offline")
-        .add_extra_param("prompt", "consent")
-        .url();
+        .add_extra_param("prompt", "consent");
+    // Autorisation incrémentale: les scopes déjà accordés sont conservés
+    if query.write {
+        request = request.add_scope(Scope::new(auth::WRITE_SCOPE.to_string()));
+    }
+    if query.captions {
+        request = request.add_scope(Scope::new(auth::CAPTIONS_SCOPE.to_string()));
+    }
+    if query.write || query.captions {
+        request = request.add_extra_param("include_granted_scopes", "true");
+    }
+    let (auth_url, _csrf_token) = request.url();
 
     info!("Redirection vers Google OAuth: {}", auth_url);
 
@@ -42,7 +85,7 @@ pub async fn login() -> HttpResponse {
 }
 
 #[get("/auth/callback")]
-pub async fn callback(query: web::Query<AuthCallbackQuery>) -> HttpResponse {
+pub async fn callback(query: web::Query<AuthCallbackQuery>, store: web::Data<Store>) -> HttpResponse {
     info!("Callback reçu avec query: {:?}", query);
 
     let client = oauth_client();
@@ -69,6 +112,23 @@ pub async fn callback(query: web::Query<AuthCallbackQuery>) -> HttpResponse {
             info!("  - refresh_token: {}", if refresh_token.is_empty() { "ABSENT" } else { "présent" });
             info!("  - expires_in: {} secondes", expires_in);
 
+            let saved = SavedToken {
+                access_token: access_token.clone(),
+                refresh_token: token.refresh_token().map(|r| r.secret().to_string()),
+                expires_in: Some(expires_in),
+                issued_at: Utc::now(),
+            };
+            let scopes = granted_scopes(token.scopes());
+            info!("  - scopes: {:?}", scopes);
+            // Les scopes du token sont connus dès la connexion, sans appel à tokeninfo
+            match auth::current_user(&store, &access_token).await {
+                Ok(user_id) => {
+                    store.remember_scopes(&access_token, scopes);
+                    save_token(&store, &user_id, saved);
+                }
+                Err(_) => warn!("Utilisateur introuvable, token non enregistré"),
+            }
+
             let redirect_url = format!(
                 "http://localhost:3000/?access_token={}&refresh_token={}&expires_in={}",
                 access_token, refresh_token, expires_in
@@ -121,7 +181,7 @@ pub async fn callback(query: web::Query<AuthCallbackQuery>) -> HttpResponse {
     }
 }
 
-async fn refresh_oauth_token(client: &BasicClient, refresh_token: &str) -> Result<SavedToken, String> {
+pub async fn refresh_oauth_token(client: &BasicClient, refresh_token: &str) -> Result<SavedToken, String> {
     match client
         .exchange_refresh_token(&oauth2::RefreshToken::new(refresh_token.to_string()))
         .request_async(async_http_client)
@@ -143,81 +203,119 @@ async fn refresh_oauth_token(client: &BasicClient, refresh_token: &str) -> Resul
     }
 }
 
-#[get("/subscriptions")]
-pub async fn subscriptions(req: HttpRequest) -> HttpResponse {
-    let access_token = match req.headers().get("authorization") {
-        Some(auth) => {
-            match auth.to_str() {
-                Ok(auth_str) => auth_str.replace("Bearer ", ""),
-                Err(_) => return HttpResponse::Unauthorized().body("Token invalide"),
+fn granted_scopes(scopes: Option<&Vec<Scope>>) -> Vec<String> {
+    scopes
+        .map(|scopes| scopes.iter().map(|s| s.to_string()).collect())
+        .unwrap_or_default()
+}
+
+// Enregistre le token de l'utilisateur pour le planificateur; le refresh_token connu est
+// conservé quand Google n'en renvoie pas. Les scopes ne sont pas stockés: require_scope
+// vérifie ceux du token présenté
+pub fn save_token(store: &Store, user_id: &str, mut token: SavedToken) {
+    store.write(|data| {
+        let user = data.users.entry(user_id.to_string()).or_default();
+        if let Some(previous) = &user.token {
+            if token.refresh_token.is_none() {
+                token.refresh_token = previous.refresh_token.clone();
             }
         }
-        None => return HttpResponse::Unauthorized().body("Aucun token d'accès fourni"),
+        user.token = Some(token);
+    });
+}
+
+#[get("/subscriptions")]
+pub async fn subscriptions(req: HttpRequest, query: web::Query<SubscriptionsQuery>, store: web::Data<Store>) -> HttpResponse {
+    let access_token = match auth::access_token(&req) {
+        Ok(token) => token,
+        Err(response) => return response,
+    };
+    let order = query.order.as_deref().unwrap_or("alphabetical");
+    // alphabetical et relevance sont triés par l'API, les autres localement
+    let api_order = match order {
+        "alphabetical" | "relevance" => order,
+        "subscribed" | "last_upload" => "alphabetical",
+        _ => {
+            return HttpResponse::BadRequest()
+                .body(format!("order invalide: {} (valeurs possibles: alphabetical, relevance, subscribed, last_upload)", order))
+        }
     };
 
     let client = Client::new();
-    let mut all_items = Vec::new();
+    let mut all_subscriptions: Vec<Subscription> = Vec::new();
     let mut page_token: Option<String> = None;
 
     loop {
-        let mut url = format!(
-            "https://www.googleapis.com/youtube/v3/subscriptions?part=snippet&mine=true&maxResults=50"
-        );
+        let mut params = vec![("part", "snippet,contentDetails"), ("mine", "true"), ("maxResults", "50"), ("order", api_order)];
         if let Some(token) = &page_token {
-            url.push_str(&format!("&pageToken={}", token));
+            params.push(("pageToken", token.as_str()));
         }
 
-        info!("Envoi de la requête à l'API YouTube: {}", url);
-        let res = match client.get(&url).bearer_auth(&access_token).send().await {
-            Ok(r) => r,
-            Err(e) => {
-                error!("Erreur reqwest pour /subscriptions: {}", e);
-                return HttpResponse::InternalServerError().body(format!("Erreur reqwest: {}", e));
-            }
+        let body = match send_json(client.get(api_url("subscriptions", &params)).bearer_auth(&access_token), "/subscriptions").await {
+            Ok(body) => body,
+            Err(response) => return response,
         };
+        info!("Réponse reçue pour /subscriptions, items: {}", body["items"].as_array().map_or(0, |items| items.len()));
+        if let Some(items) = body["items"].as_array() {
+            all_subscriptions.extend(items.iter().filter_map(subscription_from_item));
+        }
 
-        if res.status().is_success() {
-            let body: Value = match res.json().await {
-                Ok(body) => body,
-                Err(e) => {
-                    error!("Erreur de parsing de la réponse /subscriptions: {}", e);
-                    return HttpResponse::InternalServerError().body(format!("Erreur de parsing: {}", e));
-                }
-            };
-            info!("Réponse reçue pour /subscriptions, items: {}", body["items"].as_array().map_or(0, |items| items.len()));
-            if let Some(items) = body["items"].as_array() {
-                all_items.extend(items.clone());
-            }
-
-            page_token = body["nextPageToken"].as_str().map(|s| s.to_string());
-            if page_token.is_none() {
-                break;
-            }
-        } else {
-            let status = res.status();
-            let error_body = res.text().await.unwrap_or_default();
-            error!("Erreur HTTP {} pour /subscriptions: {}", status, error_body);
-            return HttpResponse::InternalServerError().body(format!("Erreur HTTP {}: {}", status, error_body));
+        page_token = body["nextPageToken"].as_str().map(|s| s.to_string());
+        if page_token.is_none() {
+            break;
         }
     }
 
-    if all_items.is_empty() {
+    if all_subscriptions.is_empty() {
         warn!("Aucun abonnement trouvé pour l'utilisateur");
     }
 
-    HttpResponse::Ok().json(all_items)
+    store.read(|data| {
+        for subscription in all_subscriptions.iter_mut() {
+            subscription.last_upload_at = data.latest_upload(&subscription.channel_id);
+        }
+    });
+    sort_subscriptions(&mut all_subscriptions, order);
+
+    HttpResponse::Ok().json(all_subscriptions)
+}
+
+// Les plus récents d'abord; les dates inconnues en dernier
+fn sort_subscriptions(items: &mut [Subscription], order: &str) {
+    match order {
+        "subscribed" => items.sort_by_key(|s| Reverse(s.subscribed_at)),
+        "last_upload" => items.sort_by_key(|s| Reverse(s.last_upload_at)),
+        _ => {}
+    }
+}
+
+fn subscription_from_item(item: &Value) -> Option<Subscription> {
+    let snippet = &item["snippet"];
+    Some(Subscription {
+        subscription_id: item["id"].as_str()?.to_string(),
+        channel_id: snippet["resourceId"]["channelId"].as_str()?.to_string(),
+        title: snippet["title"].as_str().unwrap_or("Chaîne inconnue").to_string(),
+        description: snippet["description"].as_str().unwrap_or("").to_string(),
+        thumbnail: snippet["thumbnails"]["medium"]["url"]
+            .as_str()
+            .or_else(|| snippet["thumbnails"]["default"]["url"].as_str())
+            .unwrap_or("")
+            .to_string(),
+        subscribed_at: snippet["publishedAt"]
+            .as_str()
+            .and_then(|date| DateTime::parse_from_rfc3339(date).ok())
+            .map(|date| date.with_timezone(&Utc)),
+        new_item_count: item["contentDetails"]["newItemCount"].as_u64().unwrap_or(0),
+        total_item_count: item["contentDetails"]["totalItemCount"].as_u64().unwrap_or(0),
+        last_upload_at: None,
+    })
 }
 
 #[get("/subscriptions/videos")]
-pub async fn subscriptions_videos(req: HttpRequest) -> HttpResponse {
-    let access_token = match req.headers().get("authorization") {
-        Some(auth) => {
-            match auth.to_str() {
-                Ok(auth_str) => auth_str.replace("Bearer ", ""),
-                Err(_) => return HttpResponse::Unauthorized().body("Token invalide"),
-            }
-        }
-        None => return HttpResponse::Unauthorized().body("Aucun token d'accès fourni"),
+pub async fn subscriptions_videos(req: HttpRequest, query: web::Query<FeedQuery>, store: web::Data<Store>) -> HttpResponse {
+    let access_token = match auth::access_token(&req) {
+        Ok(token) => token,
+        Err(response) => return response,
     };
 
     let client_oauth = oauth_client();
@@ -241,10 +339,27 @@ pub async fn subscriptions_videos(req: HttpRequest) -> HttpResponse {
                     Ok(token) => token,
                     Err(e) => return HttpResponse::InternalServerError().body(e),
                 };
+                // La réponse de Google ne contient en général pas de nouveau refresh_token
+                if saved.refresh_token.is_none() {
+                    saved.refresh_token = Some(refresh_token.to_string());
+                }
             }
         }
     }
 
+    let user_id = match auth::current_user(&store, &saved.access_token).await {
+        Ok(id) => id,
+        Err(response) => return response,
+    };
+
+    let group_members = match &query.group {
+        Some(name) => match store.read(|data| data.group(&user_id, name).map(|g| g.channel_ids.clone())) {
+            Some(members) => Some(members),
+            None => return HttpResponse::NotFound().body(format!("Groupe inconnu: {}", name)),
+        },
+        None => None,
+    };
+
     let client = Client::new();
     let api_key = match env::var("YOUTUBE_API_KEY") {
         Ok(key) => key,
@@ -253,320 +368,515 @@ pub async fn subscriptions_videos(req: HttpRequest) -> HttpResponse {
             return HttpResponse::InternalServerError().body("YOUTUBE_API_KEY non défini");
         }
     };
-    let mut channel_ids: Vec<String> = Vec::new();
+
+    // Le refresh_token permet au planificateur de rafraîchir le flux sans l'utilisateur
+    if saved.refresh_token.is_some() {
+        save_token(&store, &user_id, saved.clone());
+    }
+
+    // Quand le planificateur tient les chaînes à jour, le flux est servi depuis le stockage
+    let cached = store.read(|data| scheduler::fresh_channel_ids(data, &user_id, Utc::now()));
+    let from_store = cached.is_some();
+    let channel_ids = match cached {
+        Some(ids) => {
+            info!("Flux de {} servi depuis le stockage: {} chaînes", user_id, ids.len());
+            ids
+        }
+        None => {
+            let ids = match fetch_subscription_channel_ids(&client, &saved.access_token).await {
+                Ok(ids) => ids,
+                Err(e) => return HttpResponse::InternalServerError().body(e),
+            };
+            info!("Nombre total d'abonnements récupérés: {}", ids.len());
+
+            store.write(|data| {
+                let user = data.users.entry(user_id.clone()).or_default();
+                user.channel_ids = ids.clone();
+            });
+            // Les nouvelles chaînes sont abonnées au hub WebSub en arrière-plan
+            tokio::spawn(websub::ensure_subscribed(store.clone(), ids.clone()));
+            ids
+        }
+    };
+
+    if channel_ids.is_empty() {
+        warn!("Aucun abonnement trouvé, retour d'un message");
+        return HttpResponse::Ok().json(serde_json::json!({"message": "Aucun abonnement trouvé"}));
+    }
+
+    // Avec un groupe, seules ses chaînes sont interrogées, ce qui économise du quota
+    let channel_ids: Vec<String> = match group_members {
+        Some(members) => channel_ids.into_iter().filter(|id| members.contains(id)).collect(),
+        None => channel_ids,
+    };
+
+    // Les chaînes en sourdine ne coûtent aucun appel playlistItems
+    let muted = store.read(|data| data.muted_channels(&user_id, Utc::now()));
+    if !muted.is_empty() {
+        info!("{} chaînes en sourdine ignorées", muted.len());
+    }
+    let channel_ids: Vec<String> = channel_ids.into_iter().filter(|id| !muted.contains(id)).collect();
+
+    if !from_store {
+        let uploads = fetch_uploads_playlists(&client, &api_key, &channel_ids).await;
+
+        info!("Nombre total de playlists d'uploads: {}", uploads.len());
+        if uploads.is_empty() {
+            warn!("Aucune playlist d'uploads trouvée");
+            return HttpResponse::Ok().json(serde_json::json!({"message": "Aucune playlist d'uploads trouvée"}));
+        }
+
+        let futures: Vec<_> = uploads.into_iter().map(|(_, pid)| {
+            let client = client.clone();
+            let api_key = api_key.clone();
+            async move { fetch_playlist_videos(&client, &api_key, &pid, 5).await }
+        }).collect();
+
+        let fetched_videos: Vec<Video> = join_all(futures).await.into_iter().flatten().collect();
+        info!("Nombre total de vidéos collectées: {}", fetched_videos.len());
+
+        // Le stockage contient aussi les vidéos reçues via WebSub depuis le dernier appel
+        store.ingest(fetched_videos);
+    }
+    let (all_videos, rules) = store.read(|data| {
+        let videos = data.channel_videos(&channel_ids);
+        match data.users.get(&user_id) {
+            Some(user) if query.unwatched_only => (watched::hide_watched(user, videos), user.filters.clone()),
+            Some(user) => (videos, user.filters.clone()),
+            None => (videos, Vec::new()),
+        }
+    });
+
+    // Les règles de durée nécessitent des vidéos enrichies via videos.list
+    let all_videos = if filters::uses_duration(&rules) {
+        filters::enrich_durations(&store, &client, &api_key, all_videos).await
+    } else {
+        all_videos
+    };
+    let all_videos = filters::apply(&filters::compile_rules(&rules), all_videos);
+
+    if all_videos.is_empty() {
+        warn!("Aucune vidéo collectée après traitement des abonnements");
+        return HttpResponse::Ok().json(serde_json::json!({"message": "Aucune vidéo trouvée pour les abonnements"}));
+    }
+
+    let mut sorted_videos = all_videos;
+    sorted_videos.sort_by_key(|v| std::cmp::Reverse(v.published_at));
+
+    info!("Nombre de vidéos retournées: {}", sorted_videos.len());
+    HttpResponse::Ok().json(sorted_videos)
+}
+
+// Requête GET vers l'API YouTube, avec un réessai après 60 secondes en cas de 429
+async fn get_json(client: &Client, url: Url, label: &str) -> Result<Value, String> {
+    info!("Envoi de la requête pour {}", label);
+    let mut res = client
+        .get(url.clone())
+        .send()
+        .await
+        .map_err(|e| format!("Erreur reqwest pour {}: {}", label, e))?;
+
+    if res.status() == StatusCode::TOO_MANY_REQUESTS {
+        warn!("Erreur 429 pour {}, attente de 60 secondes avant réessai", label);
+        tokio::time::sleep(std::time::Duration::from_secs(60)).await;
+        res = client
+            .get(url)
+            .send()
+            .await
+            .map_err(|e| format!("Erreur reqwest pour {} (réessai): {}", label, e))?;
+    }
+
+    if !res.status().is_success() {
+        let status = res.status();
+        let error_body = res.text().await.unwrap_or_default();
+        return Err(format!("Erreur HTTP {} pour {}: {}", status, label, error_body));
+    }
+
+    res.json()
+        .await
+        .map_err(|e| format!("Erreur de parsing de la réponse {}: {}", label, e))
+}
+
+// Liste les chaînes auxquelles l'utilisateur est abonné
+pub async fn fetch_subscription_channel_ids(client: &Client, access_token: &str) -> Result<Vec<String>, String> {
+    let ids = fetch_subscription_ids(client, access_token).await?;
+    Ok(ids.into_iter().map(|(channel_id, _)| channel_id).collect())
+}
+
+// Abonnements de l'utilisateur: (channel_id, subscription_id), l'id d'abonnement
+// étant nécessaire pour se désabonner
+pub async fn fetch_subscription_ids(client: &Client, access_token: &str) -> Result<Vec<(String, String)>, String> {
+    let mut ids: Vec<(String, String)> = Vec::new();
     let mut page_token: Option<String> = None;
 
     loop {
-        let mut url = format!(
-            "https://www.googleapis.com/youtube/v3/subscriptions?part=snippet&mine=true&maxResults=50"
-        );
+        let mut params = vec![("part", "snippet"), ("mine", "true"), ("maxResults", "50")];
         if let Some(token) = &page_token {
-            url.push_str(&format!("&pageToken={}", token));
+            params.push(("pageToken", token.as_str()));
         }
+        let url = api_url("subscriptions", &params);
 
         info!("Envoi de la requête à l'API YouTube pour abonnements: {}", url);
-        let res = match client.get(&url).bearer_auth(&saved.access_token).send().await {
-            Ok(r) => r,
-            Err(e) => {
-                error!("Erreur reqwest pour /subscriptions: {}", e);
-                return HttpResponse::InternalServerError().body(format!("Erreur reqwest: {}", e));
-            }
-        };
+        let res = client.get(url).bearer_auth(access_token).send().await.map_err(|e| {
+            error!("Erreur reqwest pour /subscriptions: {}", e);
+            format!("Erreur reqwest: {}", e)
+        })?;
 
-        if res.status().is_success() {
-            let body: Value = match res.json().await {
-                Ok(body) => body,
-                Err(e) => {
-                    error!("Erreur de parsing de la réponse /subscriptions: {}", e);
-                    return HttpResponse::InternalServerError().body(format!("Erreur de parsing: {}", e));
-                }
-            };
-            let items_count = body["items"].as_array().map_or(0, |items| items.len());
-            info!("Réponse reçue pour /subscriptions, items: {}", items_count);
-            if items_count == 0 {
-                warn!("Aucun abonnement trouvé pour l'utilisateur");
-            }
+        if !res.status().is_success() {
+            let status = res.status();
+            let error_body = res.text().await.unwrap_or_default();
+            error!("Erreur HTTP {} pour /subscriptions: {}", status, error_body);
+            return Err(format!("Erreur HTTP {}: {}", status, error_body));
+        }
+
+        let body: Value = res.json().await.map_err(|e| {
+            error!("Erreur de parsing de la réponse /subscriptions: {}", e);
+            format!("Erreur de parsing: {}", e)
+        })?;
 
-            if let Some(items) = body["items"].as_array() {
-                for item in items {
-                    if let Some(channel_id) = item["snippet"]["resourceId"]["channelId"].as_str() {
-                        channel_ids.push(channel_id.to_string());
-                    }
+        let items_count = body["items"].as_array().map_or(0, |items| items.len());
+        info!("Réponse reçue pour /subscriptions, items: {}", items_count);
+        if items_count == 0 {
+            warn!("Aucun abonnement trouvé pour l'utilisateur");
+        }
+
+        if let Some(items) = body["items"].as_array() {
+            for item in items {
+                if let (Some(channel_id), Some(subscription_id)) = (item["snippet"]["resourceId"]["channelId"].as_str(), item["id"].as_str()) {
+                    ids.push((channel_id.to_string(), subscription_id.to_string()));
                 }
             }
+        }
 
-            page_token = body["nextPageToken"].as_str().map(|s| s.to_string());
-            if page_token.is_none() {
-                break;
-            }
-        } else {
-            let status = res.status();
-            let error_body = res.text().await.unwrap_or_default();
-            error!("Erreur HTTP {} pour /subscriptions: {}", status, error_body);
-            return HttpResponse::InternalServerError().body(format!("Erreur HTTP {}: {}", status, error_body));
+        page_token = body["nextPageToken"].as_str().map(|s| s.to_string());
+        if page_token.is_none() {
+            break;
         }
     }
 
-    info!("Nombre total d'abonnements récupérés: {}", channel_ids.len());
-    if channel_ids.is_empty() {
-        warn!("Aucun abonnement trouvé, retour d'un message");
-        return HttpResponse::Ok().json(serde_json::json!({"message": "Aucun abonnement trouvé"}));
-    }
+    Ok(ids)
+}
+
+// Associe chaque chaîne à sa playlist d'uploads: (channel_id, playlist_id)
+pub async fn fetch_uploads_playlists(client: &Client, api_key: &str, channel_ids: &[String]) -> Vec<(String, String)> {
+    let mut uploads: Vec<(String, String)> = Vec::new();
 
-    let mut uploads: Vec<String> = Vec::new();
     for chunk in channel_ids.chunks(50) {
         let ids = chunk.join(",");
-        let url = format!(
-            "https://www.googleapis.com/youtube/v3/channels?part=contentDetails&id={}&key={}",
-            ids, api_key
-        );
-
-        info!("Envoi de la requête pour /channels: {}", url);
-        let res = match client.get(&url).send().await {
-            Ok(r) => r,
+        let url = api_url("channels", [("part", "contentDetails"), ("id", &ids), ("key", api_key)]);
+
+        let body = match get_json(client, url, "/channels").await {
+            Ok(body) => body,
             Err(e) => {
-                error!("Erreur reqwest pour /channels: {}", e);
+                error!("{}", e);
                 continue;
             }
         };
 
-        if res.status().is_success() {
-            let body: Value = match res.json().await {
-                Ok(body) => body,
-                Err(e) => {
-                    error!("Erreur de parsing de la réponse /channels: {}", e);
-                    continue;
-                }
-            };
-            if let Some(items) = body["items"].as_array() {
-                for item in items {
-                    if let Some(pid) = item["contentDetails"]["relatedPlaylists"]["uploads"].as_str() {
-                        uploads.push(pid.to_string());
-                    } else {
-                        warn!("Aucune playlist d'uploads pour la chaîne {:?}", item["id"]);
-                    }
-                }
-            }
-        } else if res.status() == StatusCode::TOO_MANY_REQUESTS {
-            warn!("Erreur 429 pour /channels, attente de 60 secondes avant réessai");
-            tokio::time::sleep(std::time::Duration::from_secs(60)).await;
-            let retry_res = match client.get(&url).send().await {
-                Ok(r) => r,
-                Err(e) => {
-                    error!("Erreur reqwest pour /channels (réessai): {}", e);
-                    continue;
+        if let Some(items) = body["items"].as_array() {
+            for item in items {
+                match (item["id"].as_str(), item["contentDetails"]["relatedPlaylists"]["uploads"].as_str()) {
+                    (Some(channel_id), Some(pid)) => uploads.push((channel_id.to_string(), pid.to_string())),
+                    _ => warn!("Aucune playlist d'uploads pour la chaîne {:?}", item["id"]),
                 }
-            };
-            if retry_res.status().is_success() {
-                let body: Value = match retry_res.json().await {
-                    Ok(body) => body,
-                    Err(e) => {
-                        error!("Erreur de parsing de la réponse /channels (réessai): {}", e);
-                        continue;
-                    }
-                };
-                if let Some(items) = body["items"].as_array() {
-                    for item in items {
-                        if let Some(pid) = item["contentDetails"]["relatedPlaylists"]["uploads"].as_str() {
-                            uploads.push(pid.to_string());
-                        }
-                    }
-                }
-            } else {
-                let status = retry_res.status();
-                let error_body = retry_res.text().await.unwrap_or_default();
-                error!("Erreur HTTP {} pour /channels (réessai): {}", status, error_body);
             }
-        } else {
-            let status = res.status();
-            let error_body = res.text().await.unwrap_or_default();
-            error!("Erreur HTTP {} pour /channels: {}", status, error_body);
         }
     }
 
-    info!("Nombre total de playlists d'uploads: {}", uploads.len());
-    if uploads.is_empty() {
-        warn!("Aucune playlist d'uploads trouvée");
-        return HttpResponse::Ok().json(serde_json::json!({"message": "Aucune playlist d'uploads trouvée"}));
+    uploads
+}
+
+// Récupère les `max_results` dernières vidéos d'une playlist
+pub async fn fetch_playlist_videos(client: &Client, api_key: &str, pid: &str, max_results: usize) -> Vec<Video> {
+    match try_fetch_playlist_videos(client, api_key, pid, max_results).await {
+        Ok(videos) => videos,
+        Err(e) => {
+            error!("{} (playlist {})", e, pid);
+            Vec::new()
+        }
     }
+}
 
-    let futures: Vec<_> = uploads.into_iter().map(|pid| {
-        let client = client.clone();
-        let api_key = api_key.clone();
-        async move {
-            let mut videos: Vec<Video> = Vec::new();
-            let mut video_page_token: Option<String> = None;
-
-            loop {
-                let mut url = format!(
-                    "https://www.googleapis.com/youtube/v3/playlistItems?part=snippet&playlistId={}&maxResults=5&key={}",
-                    pid, api_key
-                );
-                if let Some(token) = &video_page_token {
-                    url.push_str(&format!("&pageToken={}", token));
-                }
+// Variante qui signale l'échec, pour que le planificateur puisse réessayer rapidement
+pub async fn try_fetch_playlist_videos(client: &Client, api_key: &str, pid: &str, max_results: usize) -> Result<Vec<Video>, String> {
+    let mut videos: Vec<Video> = Vec::new();
+    let mut video_page_token: Option<String> = None;
 
-                info!("Envoi de la requête pour /playlistItems: {}", url);
-                let res = match client.get(&url).send().await {
-                    Ok(r) => r,
-                    Err(e) => {
-                        error!("Erreur reqwest pour /playlistItems (playlist {}): {}", pid, e);
-                        return Vec::new();
-                    }
-                };
+    loop {
+        let page_size = max_results.min(50).to_string();
+        let mut params = vec![("part", "snippet"), ("playlistId", pid), ("maxResults", &page_size), ("key", api_key)];
+        if let Some(token) = &video_page_token {
+            params.push(("pageToken", token.as_str()));
+        }
 
-                if res.status().is_success() {
-                    let body: Value = match res.json().await {
-                        Ok(body) => body,
-                        Err(e) => {
-                            error!("Erreur de parsing de la réponse /playlistItems (playlist {}): {}", pid, e);
-                            return Vec::new();
-                        }
-                    };
-                    let video_count = body["items"].as_array().map_or(0, |items| items.len());
-                    info!("Nombre de vidéos récupérées pour la playlist {}: {}", pid, video_count);
-
-                    if let Some(video_items) = body["items"].as_array() {
-                        for video_item in video_items {
-                            if let Some(video_id) = video_item["snippet"]["resourceId"]["videoId"].as_str() {
-                                if let Some(published_at) = video_item["snippet"]["publishedAt"].as_str() {
-                                    match DateTime::parse_from_rfc3339(published_at) {
-                                        Ok(date) => {
-                                            let title = video_item["snippet"]["title"]
-                                                .as_str()
-                                                .unwrap_or("Sans titre")
-                                                .to_string();
-
-                                            let thumbnail = video_item["snippet"]["thumbnails"]["medium"]["url"]
-                                                .as_str()
-                                                .or_else(|| video_item["snippet"]["thumbnails"]["default"]["url"].as_str())
-                                                .unwrap_or("")
-                                                .to_string();
-
-                                            let channel_title = video_item["snippet"]["channelTitle"]
-                                                .as_str()
-                                                .unwrap_or("Chaîne inconnue")
-                                                .to_string();
-
-                                            videos.push(Video {
-                                                url: format!("https://www.youtube.com/watch?v={}", video_id),
-                                                video_id: video_id.to_string(),
-                                                published_at: date.with_timezone(&Utc),
-                                                title,
-                                                thumbnail,
-                                                channel_title,
-                                            });
-                                            info!("Vidéo ajoutée: {} (publiée le {})", video_id, published_at);
-                                        }
-                                        Err(e) => {
-                                            error!("Erreur lors du parsing de la date pour la vidéo {}: {}", video_id, e);
-                                        }
-                                    }
-                                } else {
-                                    warn!("Aucune date de publication pour la vidéo {}", video_id);
-                                }
-                            }
-                        }
-                    }
-
-                    video_page_token = body["nextPageToken"].as_str().map(|s| s.to_string());
-                    if video_page_token.is_none() || videos.len() >= 5 {
-                        break;
-                    }
-                } else if res.status() == StatusCode::TOO_MANY_REQUESTS {
-                    warn!("Erreur 429 pour /playlistItems (playlist {}), attente de 60 secondes avant réessai", pid);
-                    tokio::time::sleep(std::time::Duration::from_secs(60)).await;
-                    let retry_res = match client.get(&url).send().await {
-                        Ok(r) => r,
-                        Err(e) => {
-                            error!("Erreur reqwest pour /playlistItems (réessai, playlist {}): {}", pid, e);
-                            return Vec::new();
-                        }
-                    };
-                    if retry_res.status().is_success() {
-                        let body: Value = match retry_res.json().await {
-                            Ok(body) => body,
-                            Err(e) => {
-                                error!("Erreur de parsing de la réponse /playlistItems (réessai, playlist {}): {}", pid, e);
-                                return Vec::new();
-                            }
-                        };
-                        let video_count = body["items"].as_array().map_or(0, |items| items.len());
-                        info!("Nombre de vidéos récupérées pour la playlist {} (réessai): {}", pid, video_count);
-                        if let Some(video_items) = body["items"].as_array() {
-                            for video_item in video_items {
-                                if let Some(video_id) = video_item["snippet"]["resourceId"]["videoId"].as_str() {
-                                    if let Some(published_at) = video_item["snippet"]["publishedAt"].as_str() {
-                                        match DateTime::parse_from_rfc3339(published_at) {
-                                            Ok(date) => {
-                                                let title = video_item["snippet"]["title"]
-                                                    .as_str()
-                                                    .unwrap_or("Sans titre")
-                                                    .to_string();
-
-                                                let thumbnail = video_item["snippet"]["thumbnails"]["medium"]["url"]
-                                                    .as_str()
-                                                    .or_else(|| video_item["snippet"]["thumbnails"]["default"]["url"].as_str())
-                                                    .unwrap_or("")
-                                                    .to_string();
-
-                                                let channel_title = video_item["snippet"]["channelTitle"]
-                                                    .as_str()
-                                                    .unwrap_or("Chaîne inconnue")
-                                                    .to_string();
-
-                                                videos.push(Video {
-                                                    url: format!("https://www.youtube.com/watch?v={}", video_id),
-                                                    published_at: date.with_timezone(&Utc),
-                                                    video_id: video_id.to_string(),
-                                                    title,
-                                                    thumbnail,
-                                                    channel_title,
-                                                });
-                                                info!("Vidéo ajoutée: {} (publiée le {})", video_id, published_at);
-                                            }
-                                            Err(e) => {
-                                                error!("Erreur lors du parsing de la date pour la vidéo {}: {}", video_id, e);
-                                            }
-                                        }
-                                    } else {
-                                        warn!("Aucune date de publication pour la vidéo {}", video_id);
-                                    }
-                                }
-                            }
-                        }
-                        video_page_token = body["nextPageToken"].as_str().map(|s| s.to_string());
-                        if video_page_token.is_none() || videos.len() >= 5 {
-                            break;
-                        }
-                    } else {
-                        let status = retry_res.status();
-                        let error_body = retry_res.text().await.unwrap_or_default();
-                        error!("Erreur HTTP {} pour /playlistItems (réessai, playlist {}): {}", status, pid, error_body);
-                        break;
-                    }
-                } else {
-                    let status = res.status();
-                    let error_body = res.text().await.unwrap_or_default();
-                    error!("Erreur HTTP {} pour /playlistItems (playlist {}): {}", status, pid, error_body);
-                    break;
-                }
+        let body = get_json(client, api_url("playlistItems", &params), "/playlistItems").await?;
+
+        let video_count = body["items"].as_array().map_or(0, |items| items.len());
+        info!("Nombre de vidéos récupérées pour la playlist {}: {}", pid, video_count);
+
+        if let Some(video_items) = body["items"].as_array() {
+            videos.extend(video_items.iter().filter_map(video_from_playlist_item));
+        }
+
+        video_page_token = body["nextPageToken"].as_str().map(|s| s.to_string());
+        if video_page_token.is_none() || videos.len() >= max_results {
+            break;
+        }
+    }
+
+    videos.truncate(max_results);
+    Ok(videos)
+}
+
+pub fn video_from_playlist_item(video_item: &Value) -> Option<Video> {
+    let video_id = video_item["snippet"]["resourceId"]["videoId"].as_str()?;
+
+    let published_at = match video_item["snippet"]["publishedAt"].as_str() {
+        Some(published_at) => published_at,
+        None => {
+            warn!("Aucune date de publication pour la vidéo {}", video_id);
+            return None;
+        }
+    };
+
+    let date = match DateTime::parse_from_rfc3339(published_at) {
+        Ok(date) => date,
+        Err(e) => {
+            error!("Erreur lors du parsing de la date pour la vidéo {}: {}", video_id, e);
+            return None;
+        }
+    };
+
+    let title = video_item["snippet"]["title"]
+        .as_str()
+        .unwrap_or("Sans titre")
+        .to_string();
+
+    let thumbnail = video_item["snippet"]["thumbnails"]["medium"]["url"]
+        .as_str()
+        .or_else(|| video_item["snippet"]["thumbnails"]["default"]["url"].as_str())
+        .unwrap_or("")
+        .to_string();
+
+    let channel_title = video_item["snippet"]["channelTitle"]
+        .as_str()
+        .unwrap_or("Chaîne inconnue")
+        .to_string();
+
+    let channel_id = video_item["snippet"]["channelId"]
+        .as_str()
+        .unwrap_or("")
+        .to_string();
+
+    let description = video_item["snippet"]["description"]
+        .as_str()
+        .unwrap_or("")
+        .to_string();
+
+    info!("Vidéo ajoutée: {} (publiée le {})", video_id, published_at);
+    Some(Video {
+        url: format!("https://www.youtube.com/watch?v={}", video_id),
+        video_id: video_id.to_string(),
+        published_at: date.with_timezone(&Utc),
+        title,
+        thumbnail,
+        channel_title,
+        channel_id,
+        description,
+        duration_seconds: None,
+    })
+}
+
+// Récupère les métadonnées à jour d'une liste de vidéos, par lots de 50
+pub async fn fetch_videos(client: &Client, api_key: &str, video_ids: &[String]) -> Vec<Video> {
+    let mut videos: Vec<Video> = Vec::new();
+
+    for chunk in video_ids.chunks(50) {
+        let ids = chunk.join(",");
+        let url = api_url("videos", [("part", "snippet,contentDetails"), ("id", &ids), ("key", api_key)]);
+
+        let body = match get_json(client, url, "/videos").await {
+            Ok(body) => body,
+            Err(e) => {
+                error!("{}", e);
+                continue;
+            }
+        };
+
+        if let Some(items) = body["items"].as_array() {
+            videos.extend(items.iter().filter_map(video_from_videos_item));
+        }
+    }
+
+    videos
+}
+
+pub fn video_from_videos_item(item: &Value) -> Option<Video> {
+    let video_id = item["id"].as_str()?;
+    let published_at = DateTime::parse_from_rfc3339(item["snippet"]["publishedAt"].as_str()?).ok()?;
+
+    Some(Video {
+        url: format!("https://www.youtube.com/watch?v={}", video_id),
+        video_id: video_id.to_string(),
+        published_at: published_at.with_timezone(&Utc),
+        title: item["snippet"]["title"]
+            .as_str()
+            .unwrap_or("Sans titre")
+            .to_string(),
+        thumbnail: item["snippet"]["thumbnails"]["medium"]["url"]
+            .as_str()
+            .or_else(|| item["snippet"]["thumbnails"]["default"]["url"].as_str())
+            .unwrap_or("")
+            .to_string(),
+        channel_title: item["snippet"]["channelTitle"]
+            .as_str()
+            .unwrap_or("Chaîne inconnue")
+            .to_string(),
+        channel_id: item["snippet"]["channelId"]
+            .as_str()
+            .unwrap_or("")
+            .to_string(),
+        description: item["snippet"]["description"]
+            .as_str()
+            .unwrap_or("")
+            .to_string(),
+        duration_seconds: item["contentDetails"]["duration"]
+            .as_str()
+            .and_then(parse_iso8601_duration),
+    })
+}
+
+// Convertit une durée ISO 8601 de l'API ("PT1H2M3S", "P1DT2H") en secondes
+pub fn parse_iso8601_duration(duration: &str) -> Option<u64> {
+    let rest = duration.strip_prefix('P')?;
+    let mut seconds = 0;
+    let mut number = String::new();
+    let mut in_time = false;
+
+    for c in rest.chars() {
+        match c {
+            '0'..='9' => number.push(c),
+            'T' => in_time = true,
+            _ => {
+                let value: u64 = number.parse().ok()?;
+                number.clear();
+                seconds += match (c, in_time) {
+                    ('W', false) => value * 7 * 86400,
+                    ('D', false) => value * 86400,
+                    ('H', true) => value * 3600,
+                    ('M', true) => value * 60,
+                    ('S', true) => value,
+                    _ => return None,
+                };
             }
-            videos
         }
-    }).collect();
+    }
 
-    let all_videos: Vec<Video> = join_all(futures).await.into_iter().flatten().collect();
+    if number.is_empty() {
+        Some(seconds)
+    } else {
+        None
+    }
+}
 
-    info!("Nombre total de vidéos collectées: {}", all_videos.len());
-    if all_videos.is_empty() {
-        warn!("Aucune vidéo collectée après traitement des abonnements");
-        return HttpResponse::Ok().json(serde_json::json!({"message": "Aucune vidéo trouvée pour les abonnements"}));
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_iso8601_durations() {
+        let cases: &[(&str, Option<u64>)] = &[
+            ("PT0S", Some(0)),
+            ("PT45S", Some(45)),
+            ("PT4M13S", Some(253)),
+            ("PT1H", Some(3600)),
+            ("PT1H2M3S", Some(3723)),
+            ("P1DT2H", Some(93600)),
+            ("P1W", Some(604800)),
+            // Direct en cours
+            ("P0D", Some(0)),
+            ("", None),
+            ("4M13S", None),
+            ("PT4X", None),
+            ("PT12", None),
+            ("P1H", None),
+            ("PTM", None),
+        ];
+        for (input, expected) in cases {
+            assert_eq!(parse_iso8601_duration(input), *expected, "{}", input);
+        }
     }
 
-    let mut sorted_videos = all_videos;
-    sorted_videos.sort_by(|a, b| b.published_at.cmp(&a.published_at));
+    fn subscription(channel_id: &str, subscribed_at: Option<&str>, last_upload_at: Option<&str>) -> Subscription {
+        let date = |d: &str| DateTime::parse_from_rfc3339(d).unwrap().with_timezone(&Utc);
+        Subscription {
+            subscription_id: format!("sub-{}", channel_id),
+            channel_id: channel_id.to_string(),
+            title: channel_id.to_string(),
+            description: String::new(),
+            thumbnail: String::new(),
+            subscribed_at: subscribed_at.map(date),
+            new_item_count: 0,
+            total_item_count: 0,
+            last_upload_at: last_upload_at.map(date),
+        }
+    }
 
-    info!("Nombre de vidéos retournées: {}", sorted_videos.len());
-    HttpResponse::Ok().json(sorted_videos)
-}
\ No newline at end of file
+    #[test]
+    fn builds_subscription_from_api_item() {
+        let item = serde_json::json!({
+            "id": "sub-rust",
+            "snippet": {
+                "publishedAt": "2021-03-04T05:06:07.123Z",
+                "title": "Rust",
+                "description": "Le langage",
+                "resourceId": { "kind": "youtube#channel", "channelId": "UCaYhcUwRBNscFNUKTjgPFiA" },
+                "thumbnails": { "default": { "url": "https://yt3.ggpht.com/default.jpg" } }
+            },
+            "contentDetails": { "totalItemCount": 412, "newItemCount": 3 }
+        });
+        let subscription = subscription_from_item(&item).unwrap();
+        assert_eq!(subscription.subscription_id, "sub-rust");
+        assert_eq!(subscription.channel_id, "UCaYhcUwRBNscFNUKTjgPFiA");
+        assert_eq!(subscription.title, "Rust");
+        assert_eq!(subscription.description, "Le langage");
+        // Miniature "default" faute de "medium"
+        assert_eq!(subscription.thumbnail, "https://yt3.ggpht.com/default.jpg");
+        assert_eq!(subscription.subscribed_at.unwrap().to_rfc3339(), "2021-03-04T05:06:07.123+00:00");
+        assert_eq!((subscription.new_item_count, subscription.total_item_count), (3, 412));
+        assert_eq!(subscription.last_upload_at, None);
+
+        let minimal = serde_json::json!({ "id": "sub-x", "snippet": { "resourceId": { "channelId": "UCx" } } });
+        let subscription = subscription_from_item(&minimal).unwrap();
+        assert_eq!(subscription.title, "Chaîne inconnue");
+        assert_eq!(subscription.subscribed_at, None);
+
+        assert!(subscription_from_item(&serde_json::json!({ "id": "sub-x", "snippet": {} })).is_none());
+        assert!(subscription_from_item(&serde_json::json!({ "snippet": { "resourceId": { "channelId": "UCx" } } })).is_none());
+    }
+
+    #[test]
+    fn sorts_subscriptions_locally() {
+        let items = vec![
+            subscription("ancienne", Some("2019-01-01T00:00:00Z"), Some("2024-05-01T00:00:00Z")),
+            subscription("jamais_chargee", Some("2023-01-01T00:00:00Z"), None),
+            subscription("recente", Some("2022-01-01T00:00:00Z"), Some("2024-06-01T00:00:00Z")),
+            subscription("sans_date", None, Some("2024-01-01T00:00:00Z")),
+        ];
+        let order = |items: &[Subscription]| items.iter().map(|s| s.channel_id.clone()).collect::<Vec<_>>();
+
+        let mut subscribed = items.clone();
+        sort_subscriptions(&mut subscribed, "subscribed");
+        assert_eq!(order(&subscribed), vec!["jamais_chargee", "recente", "ancienne", "sans_date"]);
+
+        // Les chaînes jamais chargées dans le flux sont classées en dernier
+        let mut last_upload = items.clone();
+        sort_subscriptions(&mut last_upload, "last_upload");
+        assert_eq!(order(&last_upload), vec!["recente", "ancienne", "sans_date", "jamais_chargee"]);
+
+        // L'ordre de l'API est conservé pour alphabetical et relevance
+        let mut alphabetical = items.clone();
+        sort_subscriptions(&mut alphabetical, "alphabetical");
+        assert_eq!(order(&alphabetical), order(&items));
+    }
+}
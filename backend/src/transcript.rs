@@ -0,0 +1,273 @@
+use quick_xml::escape::unescape;
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::Reader;
+use serde::{Deserialize, Serialize};
+
+// Portion de transcription, avec début et durée en secondes
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Segment {
+    pub start: f64,
+    pub duration: f64,
+    pub text: String,
+}
+
+// Détecte le format (WebVTT, SRT ou XML timedtext de YouTube) et découpe en segments
+pub fn parse(content: &str) -> Result<Vec<Segment>, String> {
+    let content = content.trim_start_matches('\u{feff}').trim_start();
+    if content.starts_with("WEBVTT") {
+        parse_vtt(content)
+    } else if content.starts_with('<') {
+        parse_xml(content)
+    } else {
+        parse_srt(content)
+    }
+}
+
+pub fn parse_vtt(content: &str) -> Result<Vec<Segment>, String> {
+    parse_cues(content, '.')
+}
+
+pub fn parse_srt(content: &str) -> Result<Vec<Segment>, String> {
+    parse_cues(content, ',')
+}
+
+// WebVTT et SRT partagent la même structure: blocs séparés par une ligne vide,
+// contenant une ligne "début --> fin" suivie du texte
+fn parse_cues(content: &str, separator: char) -> Result<Vec<Segment>, String> {
+    let content = content.replace("\r\n", "\n").replace('\r', "\n");
+    let mut segments: Vec<Segment> = Vec::new();
+
+    for block in content.split("\n\n") {
+        let mut lines = block.lines().skip_while(|line| !line.contains("-->"));
+        let timing = match lines.next() {
+            Some(timing) => timing,
+            // En-tête WEBVTT, blocs NOTE/STYLE/REGION
+            None => continue,
+        };
+
+        let mut bounds = timing.split("-->");
+        let start = parse_timestamp(bounds.next().unwrap_or(""), separator)
+            .ok_or_else(|| format!("Horodatage invalide: {}", timing))?;
+        // Les réglages de position suivent l'horodatage de fin: "00:04.000 align:start"
+        let end_field = bounds.next().and_then(|b| b.split_whitespace().next()).unwrap_or("");
+        let end = parse_timestamp(end_field, separator).ok_or_else(|| format!("Horodatage invalide: {}", timing))?;
+
+        let text = clean_text(&decode_entities(&lines.map(strip_tags).collect::<Vec<_>>().join(" ")));
+        push_segment(&mut segments, start, end - start, text);
+    }
+
+    Ok(segments)
+}
+
+// "01:02:03.456", "02:03.456" ou avec une virgule pour SRT
+fn parse_timestamp(value: &str, separator: char) -> Option<f64> {
+    let value = value.trim();
+    let (clock, millis) = value.split_once(separator).unwrap_or((value, "0"));
+    if millis.is_empty() || !millis.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+
+    let parts: Vec<&str> = clock.split(':').collect();
+    if !(2..=3).contains(&parts.len()) {
+        return None;
+    }
+    let mut seconds = 0.0;
+    for part in parts {
+        if part.is_empty() || !part.chars().all(|c| c.is_ascii_digit()) {
+            return None;
+        }
+        seconds = seconds * 60.0 + part.parse::<f64>().ok()?;
+    }
+
+    let fraction: f64 = format!("0.{}", millis).parse().ok()?;
+    Some(seconds + fraction)
+}
+
+// Retire les balises de style et les horodatages intégrés: <c>, <i>, <00:00:01.500>
+fn strip_tags(line: &str) -> String {
+    let mut text = String::new();
+    let mut in_tag = false;
+    for c in line.chars() {
+        match c {
+            '<' => in_tag = true,
+            '>' if in_tag => in_tag = false,
+            _ if !in_tag => text.push(c),
+            _ => {}
+        }
+    }
+    text
+}
+
+// Entités autorisées par WebVTT dans le texte des sous-titres
+fn decode_entities(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&nbsp;", " ")
+        .replace("&amp;", "&")
+}
+
+// Format XML de l'API timedtext: <text start="1.2" dur="3.4"> (secondes)
+// ou <p t="1200" d="3400"> avec des <s> imbriqués (millisecondes)
+pub fn parse_xml(content: &str) -> Result<Vec<Segment>, String> {
+    let mut reader = Reader::from_str(content);
+    reader.trim_text(false);
+
+    let mut segments: Vec<Segment> = Vec::new();
+    let mut current: Option<(f64, f64, String)> = None;
+
+    loop {
+        match reader.read_event() {
+            Ok(Event::Start(e)) => match e.name().as_ref() {
+                b"text" => current = Some(timing(&e, b"start", b"dur", 1.0)?),
+                b"p" => current = Some(timing(&e, b"t", b"d", 1000.0)?),
+                _ => {}
+            },
+            Ok(Event::Text(t)) => {
+                if let Some((_, _, text)) = current.as_mut() {
+                    let raw = t.unescape().map_err(|e| e.to_string())?;
+                    // Le texte est souvent échappé deux fois: "&amp;#39;"
+                    let decoded = unescape(&raw).map(|d| d.into_owned()).unwrap_or_else(|_| raw.into_owned());
+                    text.push_str(&decoded);
+                }
+            }
+            Ok(Event::End(e)) => {
+                if matches!(e.name().as_ref(), b"text" | b"p") {
+                    if let Some((start, duration, text)) = current.take() {
+                        push_segment(&mut segments, start, duration, clean_text(&text));
+                    }
+                }
+            }
+            Ok(Event::Eof) => break,
+            Err(e) => return Err(format!("position {}: {}", reader.buffer_position(), e)),
+            _ => {}
+        }
+    }
+
+    Ok(segments)
+}
+
+fn timing(e: &BytesStart, start_name: &[u8], duration_name: &[u8], unit: f64) -> Result<(f64, f64, String), String> {
+    let mut start = None;
+    let mut duration = 0.0;
+    for attr in e.attributes().flatten() {
+        let value = String::from_utf8_lossy(&attr.value).into_owned();
+        if attr.key.as_ref() == start_name {
+            start = value.parse::<f64>().ok();
+        } else if attr.key.as_ref() == duration_name {
+            duration = value.parse::<f64>().unwrap_or(0.0);
+        }
+    }
+
+    match start {
+        Some(start) => Ok((start / unit, duration / unit, String::new())),
+        None => Err(format!("Attribut {} manquant", String::from_utf8_lossy(start_name))),
+    }
+}
+
+fn clean_text(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+// Ignore les segments vides et les répétitions des sous-titres automatiques,
+// dont chaque bloc reprend la dernière ligne du précédent
+fn push_segment(segments: &mut Vec<Segment>, start: f64, duration: f64, text: String) {
+    let text = match segments.last() {
+        Some(last) if last.text == text => return,
+        Some(last) => match text.strip_prefix(&format!("{} ", last.text)) {
+            Some(rest) => rest.to_string(),
+            None => text,
+        },
+        None => text,
+    };
+    if text.is_empty() {
+        return;
+    }
+    segments.push(Segment {
+        start: (start * 1000.0).round() / 1000.0,
+        duration: (duration.max(0.0) * 1000.0).round() / 1000.0,
+        text,
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn segment(start: f64, duration: f64, text: &str) -> Segment {
+        Segment {
+            start,
+            duration,
+            text: text.to_string(),
+        }
+    }
+
+    #[test]
+    fn parses_webvtt_fixture() {
+        let segments = parse(include_str!("../tests/fixtures/transcript.vtt")).unwrap();
+        assert_eq!(
+            segments,
+            vec![
+                segment(0.0, 3.5, "Bonjour et bienvenue"),
+                segment(3.5, 2.25, "dans ce tutoriel Rust"),
+                segment(5.75, 4.25, "on parle d'emprunts & de durées de vie"),
+                segment(3661.0, 2.0, "une heure plus tard"),
+            ]
+        );
+    }
+
+    #[test]
+    fn parses_youtube_auto_webvtt_fixture() {
+        let segments = parse(include_str!("../tests/fixtures/transcript_auto.vtt")).unwrap();
+        assert_eq!(
+            segments,
+            vec![
+                segment(0.4, 2.6, "hello everyone"),
+                segment(3.01, 2.99, "today we look at traits"),
+            ]
+        );
+    }
+
+    #[test]
+    fn parses_srt_fixture() {
+        let segments = parse(include_str!("../tests/fixtures/transcript.srt")).unwrap();
+        assert_eq!(
+            segments,
+            vec![
+                segment(1.0, 3.0, "Première ligne sur deux lignes"),
+                segment(4.5, 1.5, "Deuxième sous-titre"),
+                segment(62.0, 0.5, "Après une minute"),
+            ]
+        );
+    }
+
+    #[test]
+    fn parses_timedtext_xml_fixture() {
+        let segments = parse(include_str!("../tests/fixtures/transcript.xml")).unwrap();
+        assert_eq!(
+            segments,
+            vec![
+                segment(0.0, 2.5, "It's a trap"),
+                segment(2.5, 3.1, "Tom & Jerry <3"),
+                segment(5.6, 0.0, "sans durée"),
+            ]
+        );
+    }
+
+    #[test]
+    fn parses_timedtext_srv3_fixture() {
+        let segments = parse(include_str!("../tests/fixtures/transcript_srv3.xml")).unwrap();
+        assert_eq!(
+            segments,
+            vec![
+                segment(1.2, 2.8, "bonjour tout le monde"),
+                segment(4.0, 1.5, "c'est parti"),
+            ]
+        );
+    }
+
+    #[test]
+    fn rejects_malformed_timestamps() {
+        assert!(parse("1\n00:00:aa,000 --> 00:00:02,000\nTexte\n").is_err());
+        assert!(parse("WEBVTT\n\n00:01.000 --> x\nTexte\n").is_err());
+    }
+}
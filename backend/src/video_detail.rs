@@ -0,0 +1,244 @@
+use actix_web::{get, web, HttpRequest, HttpResponse};
+use reqwest::Client;
+use serde::Serialize;
+use serde_json::Value;
+use std::env;
+use std::error::Error;
+
+use crate::auth;
+use crate::models;
+use crate::store::Store;
+use crate::subscriptions::parse_iso8601_duration;
+use crate::youtube_api::api_url;
+
+#[derive(Serialize, Debug)]
+pub struct RegionRestriction {
+    // Codes pays ISO 3166-1; une liste "allowed" exclut tous les autres pays
+    pub allowed: Vec<String>,
+    pub blocked: Vec<String>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct LiveStreaming {
+    pub scheduled_start_time: Option<String>,
+    pub actual_start_time: Option<String>,
+    pub actual_end_time: Option<String>,
+    pub concurrent_viewers: Option<u64>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct VideoDetail {
+    pub video_id: String,
+    pub url: String,
+    pub title: String,
+    pub description: String,
+    pub thumbnail: String,
+    pub channel_id: String,
+    pub channel_title: String,
+    pub published_at: String,
+    pub tags: Vec<String>,
+    pub category_id: Option<String>,
+    pub default_language: Option<String>,
+    pub default_audio_language: Option<String>,
+    // "none", "upcoming" ou "live"
+    pub live_broadcast_content: Option<String>,
+    pub duration: Option<String>,
+    pub duration_seconds: Option<u64>,
+    pub definition: Option<String>,
+    pub dimension: Option<String>,
+    pub has_captions: bool,
+    pub licensed_content: bool,
+    // "youtube" ou "creativeCommon"
+    pub license: Option<String>,
+    pub embeddable: bool,
+    pub made_for_kids: bool,
+    pub privacy_status: Option<String>,
+    pub region_restriction: Option<RegionRestriction>,
+    pub view_count: Option<u64>,
+    pub like_count: Option<u64>,
+    pub comment_count: Option<u64>,
+    pub topic_categories: Vec<String>,
+    pub live_streaming: Option<LiveStreaming>,
+}
+
+pub fn is_video_id(value: &str) -> bool {
+    value.len() == 11 && value.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+#[get("/video/{video_id}")]
+pub async fn video(req: HttpRequest, path: web::Path<String>, store: web::Data<Store>) -> HttpResponse {
+    let api_key = match env::var("YOUTUBE_API_KEY") {
+        Ok(key) => key,
+        Err(_) => return HttpResponse::InternalServerError().body("YOUTUBE_API_KEY non défini"),
+    };
+    let video_id = path.into_inner();
+    if !is_video_id(&video_id) {
+        return HttpResponse::BadRequest().body(format!("video_id invalide: {}", video_id));
+    }
+
+    match fetch_video_detail(&Client::new(), &api_key, &video_id).await {
+        Ok(Some(detail)) => {
+            let user_id = auth::optional_user(&req, &store).await;
+            store.remember(user_id.as_deref(), models::Video::try_from(&detail).into_iter().collect());
+            HttpResponse::Ok().json(detail)
+        }
+        Ok(None) => HttpResponse::NotFound().body("Vidéo introuvable"),
+        Err(e) => HttpResponse::InternalServerError().body(format!("Erreur: {}", e)),
+    }
+}
+
+async fn fetch_video_detail(client: &Client, api_key: &str, video_id: &str) -> Result<Option<VideoDetail>, Box<dyn Error>> {
+    let url = api_url(
+        "videos",
+        [
+            ("part", "snippet,contentDetails,statistics,status,liveStreamingDetails,topicDetails"),
+            ("id", video_id),
+            ("key", api_key),
+        ],
+    );
+    let res: Value = client.get(url).send().await?.error_for_status()?.json().await?;
+
+    Ok(res["items"]
+        .as_array()
+        .and_then(|items| items.first())
+        .map(detail_from_item))
+}
+
+fn detail_from_item(item: &Value) -> VideoDetail {
+    let snippet = &item["snippet"];
+    let content = &item["contentDetails"];
+    let status = &item["status"];
+    let text = |value: &Value| value.as_str().map(|s| s.to_string());
+    let count = |value: &Value| value.as_str().and_then(|c| c.parse().ok());
+    let strings = |value: &Value| -> Vec<String> {
+        value
+            .as_array()
+            .map(|values| values.iter().filter_map(|v| v.as_str().map(|v| v.to_string())).collect())
+            .unwrap_or_default()
+    };
+
+    let video_id = item["id"].as_str().unwrap_or("").to_string();
+    let duration = text(&content["duration"]);
+
+    let restriction = &content["regionRestriction"];
+    let region_restriction = restriction.is_object().then(|| RegionRestriction {
+        allowed: strings(&restriction["allowed"]),
+        blocked: strings(&restriction["blocked"]),
+    });
+
+    let live = &item["liveStreamingDetails"];
+    let live_streaming = live.is_object().then(|| LiveStreaming {
+        scheduled_start_time: text(&live["scheduledStartTime"]),
+        actual_start_time: text(&live["actualStartTime"]),
+        actual_end_time: text(&live["actualEndTime"]),
+        concurrent_viewers: count(&live["concurrentViewers"]),
+    });
+
+    VideoDetail {
+        url: format!("https://www.youtube.com/watch?v={}", video_id),
+        video_id,
+        title: snippet["title"].as_str().unwrap_or("Sans titre").to_string(),
+        description: snippet["description"].as_str().unwrap_or("").to_string(),
+        thumbnail: snippet["thumbnails"]["maxres"]["url"]
+            .as_str()
+            .or_else(|| snippet["thumbnails"]["high"]["url"].as_str())
+            .or_else(|| snippet["thumbnails"]["default"]["url"].as_str())
+            .unwrap_or("")
+            .to_string(),
+        channel_id: snippet["channelId"].as_str().unwrap_or("").to_string(),
+        channel_title: snippet["channelTitle"].as_str().unwrap_or("Chaîne inconnue").to_string(),
+        published_at: snippet["publishedAt"].as_str().unwrap_or("").to_string(),
+        tags: strings(&snippet["tags"]),
+        category_id: text(&snippet["categoryId"]),
+        default_language: text(&snippet["defaultLanguage"]),
+        default_audio_language: text(&snippet["defaultAudioLanguage"]),
+        live_broadcast_content: text(&snippet["liveBroadcastContent"]),
+        duration_seconds: duration.as_deref().and_then(parse_iso8601_duration),
+        duration,
+        definition: text(&content["definition"]),
+        dimension: text(&content["dimension"]),
+        // L'API renvoie la chaîne "true" ou "false"
+        has_captions: content["caption"].as_str() == Some("true"),
+        licensed_content: content["licensedContent"].as_bool().unwrap_or(false),
+        license: text(&status["license"]),
+        embeddable: status["embeddable"].as_bool().unwrap_or(false),
+        made_for_kids: status["madeForKids"].as_bool().unwrap_or(false),
+        privacy_status: text(&status["privacyStatus"]),
+        region_restriction,
+        view_count: count(&item["statistics"]["viewCount"]),
+        like_count: count(&item["statistics"]["likeCount"]),
+        comment_count: count(&item["statistics"]["commentCount"]),
+        topic_categories: strings(&item["topicDetails"]["topicCategories"]),
+        live_streaming,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn items() -> Vec<Value> {
+        let body: Value = serde_json::from_str(include_str!("../tests/fixtures/videos_list.json")).unwrap();
+        body["items"].as_array().unwrap().clone()
+    }
+
+    #[test]
+    fn builds_detail_from_public_video() {
+        let detail = detail_from_item(&items()[0]);
+
+        assert_eq!(detail.video_id, "dQw4w9WgXcQ");
+        assert_eq!(detail.url, "https://www.youtube.com/watch?v=dQw4w9WgXcQ");
+        assert_eq!(detail.thumbnail, "https://i.ytimg.com/vi/dQw4w9WgXcQ/maxresdefault.jpg");
+        assert_eq!(detail.channel_id, "UCuAXFkgsw1L7xaCfnd5JJOw");
+        assert_eq!(detail.tags, vec!["rick astley", "Never Gonna Give You Up", "rickroll"]);
+        assert_eq!(detail.category_id.as_deref(), Some("10"));
+        assert_eq!(detail.duration.as_deref(), Some("PT3M33S"));
+        assert_eq!(detail.duration_seconds, Some(213));
+        assert_eq!(detail.definition.as_deref(), Some("hd"));
+        assert!(detail.has_captions && detail.licensed_content && detail.embeddable);
+        assert!(!detail.made_for_kids);
+        assert_eq!(detail.privacy_status.as_deref(), Some("public"));
+
+        let restriction = detail.region_restriction.unwrap();
+        assert!(restriction.allowed.is_empty());
+        assert_eq!(restriction.blocked, vec!["DE", "RU"]);
+
+        assert_eq!(detail.view_count, Some(1523477193));
+        assert_eq!(detail.like_count, Some(17893346));
+        assert_eq!(detail.comment_count, Some(2341087));
+        assert_eq!(detail.topic_categories.len(), 2);
+
+        let live = detail.live_streaming.unwrap();
+        assert_eq!(live.actual_end_time.as_deref(), Some("2009-10-25T07:03:33Z"));
+        assert_eq!(live.scheduled_start_time, None);
+        assert_eq!(live.concurrent_viewers, None);
+    }
+
+    #[test]
+    fn tolerates_missing_parts_of_private_video() {
+        // Vidéo privée: ni contentDetails, ni statistics, ni topicDetails
+        let detail = detail_from_item(&items()[1]);
+
+        assert_eq!(detail.video_id, "oHg5SJYRHA0");
+        assert_eq!(detail.thumbnail, "https://i.ytimg.com/img/no_thumbnail.jpg");
+        assert!(detail.tags.is_empty());
+        assert_eq!(detail.duration, None);
+        assert_eq!(detail.duration_seconds, None);
+        assert!(!detail.has_captions && !detail.licensed_content && !detail.embeddable);
+        assert_eq!(detail.privacy_status.as_deref(), Some("private"));
+        assert!(detail.region_restriction.is_none());
+        assert_eq!((detail.view_count, detail.like_count, detail.comment_count), (None, None, None));
+        assert!(detail.topic_categories.is_empty());
+        assert!(detail.live_streaming.is_none());
+    }
+
+    #[test]
+    fn tolerates_bare_item() {
+        let detail = detail_from_item(&serde_json::json!({ "id": "9bZkp7q19f0" }));
+        assert_eq!(detail.title, "Sans titre");
+        assert_eq!(detail.channel_title, "Chaîne inconnue");
+        assert_eq!(detail.thumbnail, "");
+        assert_eq!(detail.published_at, "");
+        assert!(detail.category_id.is_none() && detail.license.is_none());
+    }
+}
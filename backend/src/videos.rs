@@ -1,126 +1,167 @@
-use actix_web::{get, web, HttpResponse, Responder};
+use actix_web::{get, web, HttpRequest, HttpResponse, Responder};
 use reqwest::Client;
-use serde::{Deserialize, Serialize};
+use serde::Deserialize;
 use serde_json::Value;
+use std::collections::HashMap;
 use std::error::Error;
 use std::env;
 
-#[derive(Serialize, Deserialize, Debug)]
-pub struct Video {
-    pub video_id: String,
-    pub title: String,
-    pub description: String,
-    pub thumbnail: String,
-    pub channel_title: String,
-    pub published_at: String,
-    pub duration: String,
-    pub view_count: String,
+use crate::auth;
+use crate::channels;
+use crate::models;
+use crate::resolver;
+use crate::store::Store;
+use crate::youtube_api::api_url;
+
+// Même forme que les résultats de /search/{query}
+pub use crate::search_video::Video;
+
+// Nombre de vidéos retournées par défaut et au maximum
+const DEFAULT_LIMIT: usize = 20;
+const MAX_LIMIT: usize = 500;
+
+#[derive(Deserialize, Debug)]
+pub struct VideosQuery {
+    limit: Option<usize>,
+}
+
+impl VideosQuery {
+    pub fn limit(&self) -> usize {
+        self.limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT)
+    }
 }
 
 #[get("/videos/{query}")]
-pub async fn videos(query: web::Path<String>) -> impl Responder {
+pub async fn videos(req: HttpRequest, query: web::Path<String>, params: web::Query<VideosQuery>, store: web::Data<Store>) -> impl Responder {
     let api_key = env::var("YOUTUBE_API_KEY").expect("YOUTUBE_API_KEY non défini");
     let query = query.into_inner();
 
-    match get_videos(&api_key, &query).await {
-        Ok(videos) => HttpResponse::Ok().json(videos),
+    match get_videos(&store, &api_key, &query, params.limit()).await {
+        Ok(videos) => {
+            let user_id = auth::optional_user(&req, &store).await;
+            store.remember(user_id.as_deref(), videos.iter().filter_map(|v| models::Video::try_from(v).ok()).collect());
+            HttpResponse::Ok().json(videos)
+        }
         Err(e) => HttpResponse::InternalServerError().body(format!("Erreur: {}", e)),
     }
 }
 
-async fn get_videos(api_key: &str, query: &str) -> Result<Vec<Video>, Box<dyn Error>> {
+async fn get_videos(store: &Store, api_key: &str, query: &str, limit: usize) -> Result<Vec<Video>, Box<dyn Error>> {
     let client = Client::new();
 
-    // Étape 1: Chercher la chaîne
-    let search_url = format!(
-        "https://www.googleapis.com/youtube/v3/search?part=snippet&type=channel&q={}&key={}",
-        query, api_key
-    );
-    let search_res: Value = client.get(&search_url).send().await?.json().await?;
-    let channel_id = search_res["items"]
-        .as_array()
-        .and_then(|items| items.get(0))
-        .and_then(|item| item["id"]["channelId"].as_str())
-        .ok_or("Impossible de récupérer le channelId")?;
-
-    // Étape 2: Playlist des uploads
-    let url = format!(
-        "https://www.googleapis.com/youtube/v3/channels?part=contentDetails&id={}&key={}",
-        channel_id, api_key
-    );
-    let res: Value = client.get(&url).send().await?.json().await?;
-    let uploads_playlist = res["items"]
-        .as_array()
-        .and_then(|items| items.get(0))
-        .and_then(|item| item["contentDetails"]["relatedPlaylists"]["uploads"].as_str())
+    // Étape 1: Identifier la chaîne (id, @handle, lien collé), sinon la chercher
+    let channel_id = match resolver::resolve(&client, api_key, query).await? {
+        Some(channel_id) => channel_id,
+        None => {
+            let search_url = api_url("search", [("part", "snippet"), ("type", "channel"), ("q", query), ("key", api_key)]);
+            let search_res: Value = client.get(search_url).send().await?.error_for_status()?.json().await?;
+            search_res["items"]
+                .as_array()
+                .and_then(|items| items.first())
+                .and_then(|item| item["id"]["channelId"].as_str())
+                .ok_or("Impossible de récupérer le channelId")?
+                .to_string()
+        }
+    };
+
+    get_channel_videos(store, &client, api_key, &channel_id, limit).await
+}
+
+// Dernières vidéos publiées par une chaîne dont l'id est connu, au plus `limit`
+pub async fn get_channel_videos(store: &Store, client: &Client, api_key: &str, channel_id: &str, limit: usize) -> Result<Vec<Video>, Box<dyn Error>> {
+    // Étape 2: Playlist des uploads, déjà connue localement la plupart du temps
+    let uploads_playlist = channels::uploads_playlist(store, client, api_key, channel_id)
+        .await?
         .ok_or("Impossible de récupérer la playlist")?;
+    let uploads_playlist = uploads_playlist.as_str();
 
-    // Étape 3: Récupérer les vidéos (avec snippet et contentDetails)
-    let url = format!(
-        "https://www.googleapis.com/youtube/v3/playlistItems?part=snippet&playlistId={}&maxResults=20&key={}",
-        uploads_playlist, api_key
-    );
-    let res: Value = client.get(&url).send().await?.json().await?;
-
-    let mut video_list = vec![];
-    if let Some(items) = res["items"].as_array() {
-        for item in items {
-            if let Some(video_id) = item["snippet"]["resourceId"]["videoId"].as_str() {
-                // Récupérer les stats de la vidéo
-                let stats_url = format!(
-                    "https://www.googleapis.com/youtube/v3/videos?part=contentDetails,statistics&id={}&key={}",
-                    video_id, api_key
-                );
-                let stats_res: Value = client.get(&stats_url).send().await?.json().await?;
-
-                let duration = stats_res["items"]
-                    .as_array()
-                    .and_then(|items| items.get(0))
-                    .and_then(|item| item["contentDetails"]["duration"].as_str())
-                    .unwrap_or("PT0S")
-                    .to_string();
-
-                let view_count = stats_res["items"]
-                    .as_array()
-                    .and_then(|items| items.get(0))
-                    .and_then(|item| item["statistics"]["viewCount"].as_str())
-                    .unwrap_or("0")
-                    .to_string();
-
-                let video = Video {
-                    video_id: video_id.to_string(),
-                    title: item["snippet"]["title"]
-                        .as_str()
-                        .unwrap_or("Titre inconnu")
-                        .to_string(),
-                    description: item["snippet"]["description"]
-                        .as_str()
-                        .unwrap_or("")
-                        .to_string(),
-                    thumbnail: item["snippet"]["thumbnails"]["medium"]["url"]
-                        .as_str()
-                        .unwrap_or("https://via.placeholder.com/320x180")
-                        .to_string(),
-                    channel_title: item["snippet"]["channelTitle"]
-                        .as_str()
-                        .unwrap_or("Chaîne inconnue")
-                        .to_string(),
-                    published_at: item["snippet"]["publishedAt"]
-                        .as_str()
-                        .unwrap_or("")
-                        .to_string(),
-                    duration,
-                    view_count,
-                };
-
-                video_list.push(video);
+    // Étape 3: Parcourir la playlist page par page jusqu'à `limit` vidéos
+    let mut items: Vec<Value> = Vec::new();
+    let mut page_token: Option<String> = None;
+    loop {
+        let page_size = (limit - items.len()).min(50).to_string();
+        let mut params = vec![("part", "snippet"), ("playlistId", uploads_playlist), ("maxResults", &page_size), ("key", api_key)];
+        if let Some(token) = &page_token {
+            params.push(("pageToken", token.as_str()));
+        }
+        let res: Value = client.get(api_url("playlistItems", &params)).send().await?.error_for_status()?.json().await?;
+
+        if let Some(page) = res["items"].as_array() {
+            items.extend(page.iter().filter(|item| item["snippet"]["resourceId"]["videoId"].is_string()).cloned());
+        }
+
+        page_token = res["nextPageToken"].as_str().map(|s| s.to_string());
+        if page_token.is_none() || items.len() >= limit {
+            break;
+        }
+    }
+    items.truncate(limit);
+
+    // Étape 4: Durées et vues en un appel videos.list par lot de 50
+    let video_ids: Vec<&str> = items
+        .iter()
+        .filter_map(|item| item["snippet"]["resourceId"]["videoId"].as_str())
+        .collect();
+    let mut details: HashMap<String, (String, String)> = HashMap::new();
+    for chunk in video_ids.chunks(50) {
+        let ids = chunk.join(",");
+        let stats_url = api_url("videos", [("part", "contentDetails,statistics"), ("id", &ids), ("key", api_key)]);
+        let stats_res: Value = client.get(stats_url).send().await?.error_for_status()?.json().await?;
+
+        for detail in stats_res["items"].as_array().into_iter().flatten() {
+            if let Some(id) = detail["id"].as_str() {
+                let duration = detail["contentDetails"]["duration"].as_str().unwrap_or("PT0S").to_string();
+                let view_count = detail["statistics"]["viewCount"].as_str().unwrap_or("0").to_string();
+                details.insert(id.to_string(), (duration, view_count));
             }
         }
     }
 
+    // Étape 5: Assembler dans l'ordre de la playlist
+    let video_list: Vec<Video> = items
+        .iter()
+        .filter_map(|item| {
+            let video_id = item["snippet"]["resourceId"]["videoId"].as_str()?;
+            let (duration, view_count) = details
+                .get(video_id)
+                .cloned()
+                .unwrap_or_else(|| ("PT0S".to_string(), "0".to_string()));
+
+            Some(Video {
+                video_id: video_id.to_string(),
+                title: item["snippet"]["title"]
+                    .as_str()
+                    .unwrap_or("Titre inconnu")
+                    .to_string(),
+                description: item["snippet"]["description"]
+                    .as_str()
+                    .unwrap_or("")
+                    .to_string(),
+                thumbnail: item["snippet"]["thumbnails"]["medium"]["url"]
+                    .as_str()
+                    .unwrap_or("https://via.placeholder.com/320x180")
+                    .to_string(),
+                channel_title: item["snippet"]["channelTitle"]
+                    .as_str()
+                    .unwrap_or("Chaîne inconnue")
+                    .to_string(),
+                channel_id: item["snippet"]["channelId"]
+                    .as_str()
+                    .unwrap_or("")
+                    .to_string(),
+                published_at: item["snippet"]["publishedAt"]
+                    .as_str()
+                    .unwrap_or("")
+                    .to_string(),
+                duration,
+                view_count,
+            })
+        })
+        .collect();
+
     if video_list.is_empty() {
         return Err("Aucune vidéo trouvée".into());
     }
 
     Ok(video_list)
-}
\ No newline at end of file
+}
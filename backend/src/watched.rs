@@ -0,0 +1,209 @@
+use actix_web::{delete, get, post, web, HttpRequest, HttpResponse};
+use chrono::{DateTime, Utc};
+use log::info;
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::Entry;
+
+use crate::auth;
+use crate::models::Video;
+use crate::store::{Store, StoreData, UserData};
+use crate::video_detail::is_video_id;
+
+#[derive(Serialize, Debug)]
+pub struct WatchedVideo {
+    pub video_id: String,
+    pub watched_at: DateTime<Utc>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct MarkBeforeRequest {
+    pub before: DateTime<Utc>,
+}
+
+#[get("/watched")]
+pub async fn list_watched(req: HttpRequest, store: web::Data<Store>) -> HttpResponse {
+    let user_id = match auth::authenticated_user(&req, &store).await {
+        Ok(id) => id,
+        Err(response) => return response,
+    };
+
+    let mut watched: Vec<WatchedVideo> = store.read(|data| {
+        data.users
+            .get(&user_id)
+            .map(|user| {
+                user.watched
+                    .iter()
+                    .map(|(video_id, watched_at)| WatchedVideo {
+                        video_id: video_id.clone(),
+                        watched_at: *watched_at,
+                    })
+                    .collect()
+            })
+            .unwrap_or_default()
+    });
+    watched.sort_by_key(|w| std::cmp::Reverse(w.watched_at));
+
+    HttpResponse::Ok().json(watched)
+}
+
+#[post("/watched/{video_id}")]
+pub async fn mark_watched(req: HttpRequest, path: web::Path<String>, store: web::Data<Store>) -> HttpResponse {
+    let user_id = match auth::authenticated_user(&req, &store).await {
+        Ok(id) => id,
+        Err(response) => return response,
+    };
+    let video_id = path.into_inner();
+    if !is_video_id(&video_id) {
+        return HttpResponse::BadRequest().body(format!("video_id invalide: {}", video_id));
+    }
+
+    store.write(|data| {
+        data.users
+            .entry(user_id)
+            .or_default()
+            .watched
+            .entry(video_id)
+            .or_insert_with(Utc::now);
+    });
+
+    HttpResponse::NoContent().finish()
+}
+
+#[delete("/watched/{video_id}")]
+pub async fn mark_unwatched(req: HttpRequest, path: web::Path<String>, store: web::Data<Store>) -> HttpResponse {
+    let user_id = match auth::authenticated_user(&req, &store).await {
+        Ok(id) => id,
+        Err(response) => return response,
+    };
+    let video_id = path.into_inner();
+    if !is_video_id(&video_id) {
+        return HttpResponse::BadRequest().body(format!("video_id invalide: {}", video_id));
+    }
+
+    store.write(|data| {
+        if let Some(user) = data.users.get_mut(&user_id) {
+            user.watched.remove(&video_id);
+        }
+    });
+
+    HttpResponse::NoContent().finish()
+}
+
+// Marque comme vues toutes les vidéos du flux publiées avant la date donnée
+#[post("/watched")]
+pub async fn mark_watched_before(req: HttpRequest, body: web::Json<MarkBeforeRequest>, store: web::Data<Store>) -> HttpResponse {
+    let user_id = match auth::authenticated_user(&req, &store).await {
+        Ok(id) => id,
+        Err(response) => return response,
+    };
+
+    let marked = store.write(|data| mark_before(data, &user_id, body.before, Utc::now()));
+
+    info!("{} vidéos marquées comme vues pour {} (avant {})", marked, user_id, body.before);
+    HttpResponse::Ok().json(serde_json::json!({ "marked": marked }))
+}
+
+// Ne compte que les vidéos nouvellement marquées: celles déjà vues gardent leur date
+pub fn mark_before(data: &mut StoreData, user_id: &str, before: DateTime<Utc>, now: DateTime<Utc>) -> usize {
+    let channel_ids = data.user_channels(user_id);
+    let video_ids: Vec<String> = data
+        .channel_videos(&channel_ids)
+        .into_iter()
+        .filter(|v| v.published_at < before)
+        .map(|v| v.video_id)
+        .collect();
+
+    let user = data.users.entry(user_id.to_string()).or_default();
+    let mut marked = 0;
+    for video_id in video_ids {
+        if let Entry::Vacant(entry) = user.watched.entry(video_id) {
+            entry.insert(now);
+            marked += 1;
+        }
+    }
+    marked
+}
+
+pub fn hide_watched(user: &UserData, videos: Vec<Video>) -> Vec<Video> {
+    videos
+        .into_iter()
+        .filter(|v| !user.watched.contains_key(&v.video_id))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    fn video(video_id: &str, channel_id: &str, published_at: DateTime<Utc>) -> Video {
+        Video {
+            url: format!("https://www.youtube.com/watch?v={}", video_id),
+            video_id: video_id.to_string(),
+            published_at,
+            title: String::new(),
+            thumbnail: String::new(),
+            channel_title: String::new(),
+            channel_id: channel_id.to_string(),
+            description: String::new(),
+            duration_seconds: None,
+        }
+    }
+
+    fn store_data(now: DateTime<Utc>) -> StoreData {
+        let mut data = StoreData::default();
+        data.users.insert(
+            "UC_user".to_string(),
+            UserData {
+                channel_ids: vec!["UC_a".to_string()],
+                ..UserData::default()
+            },
+        );
+        data.upsert_video(video("ancienne_01", "UC_a", now - Duration::days(3)));
+        data.upsert_video(video("deja_vue_01", "UC_a", now - Duration::days(2)));
+        data.upsert_video(video("recente_001", "UC_a", now));
+        data.upsert_video(video("autre_chain", "UC_b", now - Duration::days(3)));
+        data
+    }
+
+    #[test]
+    fn marks_only_older_subscribed_videos() {
+        let now = Utc::now();
+        let mut data = store_data(now);
+        let watched_at = now - Duration::days(1);
+        data.users.get_mut("UC_user").unwrap().watched.insert("deja_vue_01".to_string(), watched_at);
+
+        let marked = mark_before(&mut data, "UC_user", now - Duration::hours(1), now);
+
+        assert_eq!(marked, 1);
+        let watched = &data.users["UC_user"].watched;
+        assert_eq!(watched.get("ancienne_01"), Some(&now));
+        assert_eq!(watched.get("deja_vue_01"), Some(&watched_at));
+        assert!(!watched.contains_key("recente_001"));
+        assert!(!watched.contains_key("autre_chain"));
+    }
+
+    #[test]
+    fn marking_twice_counts_nothing_new() {
+        let now = Utc::now();
+        let mut data = store_data(now);
+        assert_eq!(mark_before(&mut data, "UC_user", now + Duration::hours(1), now), 3);
+        assert_eq!(mark_before(&mut data, "UC_user", now + Duration::hours(1), now), 0);
+    }
+
+    #[test]
+    fn hides_watched_videos_from_feed() {
+        let now = Utc::now();
+        let mut data = store_data(now);
+        data.users.get_mut("UC_user").unwrap().watched.insert("ancienne_01".to_string(), now);
+
+        let videos = data.channel_videos(&data.user_channels("UC_user"));
+        let mut visible: Vec<String> = hide_watched(&data.users["UC_user"], videos)
+            .into_iter()
+            .map(|v| v.video_id)
+            .collect();
+        visible.sort();
+
+        assert_eq!(visible, vec!["deja_vue_01", "recente_001"]);
+    }
+}
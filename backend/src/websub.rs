@@ -0,0 +1,639 @@
+use actix_web::{get, post, web, HttpRequest, HttpResponse};
+use chrono::{DateTime, Duration, Utc};
+use hmac::{Hmac, Mac};
+use log::{error, info, warn};
+use quick_xml::events::Event;
+use quick_xml::Reader;
+use reqwest::Client;
+use sha1::Sha1;
+use std::collections::HashMap;
+use std::env;
+
+use crate::models::Video;
+use crate::store::{Store, WebSubLease};
+
+const TOPIC_BASE: &str = "https://www.youtube.com/xml/feeds/videos.xml?channel_id=";
+
+// Marge avant expiration à partir de laquelle un bail est renouvelé
+const RENEW_MARGIN_HOURS: i64 = 12;
+// Délai après lequel une demande jamais vérifiée par le hub est renvoyée
+const PENDING_RETRY_HOURS: i64 = 1;
+
+// Durée de bail demandée par défaut, 5 jours
+const DEFAULT_LEASE_SECONDS: u64 = 432000;
+
+type HmacSha1 = Hmac<Sha1>;
+
+#[derive(Clone, Debug)]
+pub struct WebSubConfig {
+    pub hub_url: String,
+    pub callback_url: String,
+    pub secret: String,
+    pub lease_seconds: u64,
+}
+
+impl WebSubConfig {
+    // WebSub n'est actif que si l'URL de callback publique et le secret sont définis
+    pub fn from_env() -> Option<WebSubConfig> {
+        let callback_url = env::var("WEBSUB_CALLBACK_URL").ok().filter(|s| !s.is_empty())?;
+        let secret = env::var("WEBSUB_SECRET").ok().filter(|s| !s.is_empty())?;
+        let hub_url = env::var("WEBSUB_HUB_URL")
+            .unwrap_or_else(|_| "https://pubsubhubbub.appspot.com/subscribe".to_string());
+        let lease_seconds = env::var("WEBSUB_LEASE_SECONDS")
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .unwrap_or(DEFAULT_LEASE_SECONDS);
+
+        Some(WebSubConfig {
+            hub_url,
+            callback_url,
+            secret,
+            lease_seconds,
+        })
+    }
+}
+
+pub fn topic_url(channel_id: &str) -> String {
+    format!("{}{}", TOPIC_BASE, channel_id)
+}
+
+fn channel_id_from_topic(topic: &str) -> Option<&str> {
+    topic.strip_prefix(TOPIC_BASE).filter(|id| !id.is_empty())
+}
+
+// Envoie une demande (dés)abonnement au hub, la vérification arrive ensuite sur le callback
+pub async fn request_subscription(client: &Client, config: &WebSubConfig, channel_id: &str, mode: &str) -> Result<(), String> {
+    let topic = topic_url(channel_id);
+    let lease_seconds = config.lease_seconds.to_string();
+    let params = [
+        ("hub.callback", config.callback_url.as_str()),
+        ("hub.mode", mode),
+        ("hub.topic", topic.as_str()),
+        ("hub.verify", "async"),
+        ("hub.secret", config.secret.as_str()),
+        ("hub.lease_seconds", lease_seconds.as_str()),
+    ];
+
+    let res = client
+        .post(&config.hub_url)
+        .form(&params)
+        .send()
+        .await
+        .map_err(|e| format!("Erreur reqwest pour le hub WebSub: {}", e))?;
+
+    if res.status().is_success() {
+        Ok(())
+    } else {
+        let status = res.status();
+        let error_body = res.text().await.unwrap_or_default();
+        Err(format!("Erreur HTTP {} du hub WebSub: {}", status, error_body))
+    }
+}
+
+async fn subscribe_channel(client: &Client, config: &WebSubConfig, store: &Store, channel_id: &str) {
+    // Le bail est enregistré avant l'envoi: le hub peut vérifier avant la fin de la requête
+    store.write(|data| {
+        let lease = data.websub.entry(channel_id.to_string()).or_insert_with(|| WebSubLease {
+            topic: topic_url(channel_id),
+            requested_at: Utc::now(),
+            expires_at: None,
+            unsubscribe_requested_at: None,
+        });
+        lease.requested_at = Utc::now();
+        lease.unsubscribe_requested_at = None;
+    });
+
+    match request_subscription(client, config, channel_id, "subscribe").await {
+        Ok(()) => info!("Abonnement WebSub demandé pour la chaîne {}", channel_id),
+        Err(e) => error!("Abonnement WebSub impossible pour la chaîne {}: {}", channel_id, e),
+    }
+}
+
+// Abonne au hub les chaînes qui n'ont pas encore de bail
+pub async fn ensure_subscribed(store: web::Data<Store>, channel_ids: Vec<String>) {
+    let config = match WebSubConfig::from_env() {
+        Some(config) => config,
+        None => return,
+    };
+
+    // Une chaîne en cours de désabonnement est réabonnée
+    let missing: Vec<String> = store.read(|data| {
+        channel_ids
+            .into_iter()
+            .filter(|id| data.websub.get(id).is_none_or(|lease| lease.unsubscribe_requested_at.is_some()))
+            .collect()
+    });
+
+    if missing.is_empty() {
+        return;
+    }
+
+    info!("Abonnement WebSub de {} nouvelles chaînes", missing.len());
+    let client = Client::new();
+    for channel_id in missing {
+        subscribe_channel(&client, &config, &store, &channel_id).await;
+    }
+}
+
+// Désabonne du hub les chaînes qui ne sont plus suivies par aucun utilisateur
+pub async fn release_unfollowed(store: web::Data<Store>, channel_ids: Vec<String>) {
+    let config = match WebSubConfig::from_env() {
+        Some(config) => config,
+        None => return,
+    };
+
+    let unfollowed: Vec<String> = store.read(|data| {
+        channel_ids
+            .into_iter()
+            .filter(|id| data.websub.contains_key(id) && !data.users.values().any(|user| user.channel_ids.contains(id)))
+            .collect()
+    });
+
+    let client = Client::new();
+    for channel_id in unfollowed {
+        // Seul un désabonnement enregistré ici sera confirmé lors de la vérification
+        store.write(|data| {
+            if let Some(lease) = data.websub.get_mut(&channel_id) {
+                lease.unsubscribe_requested_at = Some(Utc::now());
+            }
+        });
+        match request_subscription(&client, &config, &channel_id, "unsubscribe").await {
+            Ok(()) => info!("Désabonnement WebSub demandé pour la chaîne {}", channel_id),
+            Err(e) => error!("Désabonnement WebSub impossible pour la chaîne {}: {}", channel_id, e),
+        }
+    }
+}
+
+// Tâche de fond: renouvelle les baux proches de l'expiration
+pub async fn renew_leases(store: web::Data<Store>) {
+    let config = match WebSubConfig::from_env() {
+        Some(config) => config,
+        None => {
+            info!("WEBSUB_CALLBACK_URL ou WEBSUB_SECRET non défini, WebSub désactivé");
+            return;
+        }
+    };
+
+    let client = Client::new();
+    let mut interval = tokio::time::interval(std::time::Duration::from_secs(3600));
+
+    loop {
+        interval.tick().await;
+
+        let now = Utc::now();
+        let to_renew: Vec<String> = store.read(|data| {
+            data.websub
+                .iter()
+                .filter(|(_, lease)| lease.unsubscribe_requested_at.is_none())
+                .filter(|(_, lease)| match lease.expires_at {
+                    Some(expires_at) => expires_at - Duration::hours(RENEW_MARGIN_HOURS) <= now,
+                    None => lease.requested_at + Duration::hours(PENDING_RETRY_HOURS) <= now,
+                })
+                .map(|(channel_id, _)| channel_id.clone())
+                .collect()
+        });
+
+        if !to_renew.is_empty() {
+            info!("Renouvellement de {} baux WebSub", to_renew.len());
+        }
+        for channel_id in to_renew {
+            subscribe_channel(&client, &config, &store, &channel_id).await;
+        }
+    }
+}
+
+// Une vérification n'est confirmée que si elle correspond à une demande envoyée par ce serveur:
+// abonnement en attente (nouveau ou renouvellement) ou désabonnement demandé
+fn expects_verification(lease: &WebSubLease, mode: &str, now: DateTime<Utc>) -> bool {
+    let pending_subscribe = lease.unsubscribe_requested_at.is_none()
+        && (lease.expires_at.is_none() || lease.requested_at + Duration::hours(PENDING_RETRY_HOURS) > now);
+    match mode {
+        "subscribe" | "denied" => pending_subscribe,
+        "unsubscribe" => lease.unsubscribe_requested_at.is_some(),
+        _ => false,
+    }
+}
+
+#[get("/websub/callback")]
+pub async fn verify(query: web::Query<HashMap<String, String>>, store: web::Data<Store>) -> HttpResponse {
+    let (mode, topic) = match (query.get("hub.mode"), query.get("hub.topic")) {
+        (Some(mode), Some(topic)) => (mode.as_str(), topic.as_str()),
+        _ => return HttpResponse::BadRequest().body("hub.mode ou hub.topic manquant"),
+    };
+    if !matches!(mode, "subscribe" | "unsubscribe" | "denied") {
+        return HttpResponse::BadRequest().body("hub.mode invalide");
+    }
+
+    let channel_id = match channel_id_from_topic(topic) {
+        Some(id) => id.to_string(),
+        None => return HttpResponse::NotFound().body("Topic inconnu"),
+    };
+
+    let now = Utc::now();
+    let expected = store.read(|data| data.websub.get(&channel_id).is_some_and(|lease| expects_verification(lease, mode, now)));
+    if !expected {
+        warn!("Vérification WebSub {} non demandée pour le topic {}", mode, topic);
+        return HttpResponse::NotFound().body("Aucune demande en attente pour ce topic");
+    }
+
+    if mode == "denied" {
+        store.write(|data| data.websub.remove(&channel_id));
+        warn!("Abonnement WebSub refusé par le hub pour la chaîne {}: {:?}", channel_id, query.get("hub.reason"));
+        return HttpResponse::Ok().finish();
+    }
+
+    let challenge = match query.get("hub.challenge").filter(|c| !c.is_empty()) {
+        Some(challenge) => challenge.clone(),
+        None => return HttpResponse::BadRequest().body("hub.challenge manquant"),
+    };
+
+    if mode == "subscribe" {
+        // Sans durée fournie par le hub, on retient celle qui a été demandée: un bail sans
+        // expiration resterait sinon en attente et serait redemandé indéfiniment
+        let lease_seconds = query
+            .get("hub.lease_seconds")
+            .and_then(|s| s.parse::<i64>().ok())
+            .unwrap_or_else(|| WebSubConfig::from_env().map_or(DEFAULT_LEASE_SECONDS, |config| config.lease_seconds) as i64);
+        store.write(|data| {
+            if let Some(lease) = data.websub.get_mut(&channel_id) {
+                lease.expires_at = Some(now + Duration::seconds(lease_seconds));
+            }
+        });
+        info!("Abonnement WebSub confirmé pour la chaîne {} ({} secondes)", channel_id, lease_seconds);
+    } else {
+        store.write(|data| data.websub.remove(&channel_id));
+        info!("Désabonnement WebSub confirmé pour la chaîne {}", channel_id);
+    }
+
+    HttpResponse::Ok()
+        .content_type("text/plain")
+        .body(challenge)
+}
+
+#[post("/websub/callback")]
+pub async fn notify(req: HttpRequest, body: web::Bytes, store: web::Data<Store>) -> HttpResponse {
+    let config = match WebSubConfig::from_env() {
+        Some(config) => config,
+        None => return HttpResponse::NotFound().finish(),
+    };
+
+    // Une notification mal signée est acquittée mais ignorée, comme le prévoit la spécification
+    let signature = req.headers().get("x-hub-signature").and_then(|v| v.to_str().ok());
+    match signature {
+        Some(signature) if verify_signature(&config.secret, &body, signature) => {}
+        _ => {
+            warn!("Notification WebSub avec une signature absente ou invalide, ignorée");
+            return HttpResponse::Ok().finish();
+        }
+    }
+
+    let xml = match std::str::from_utf8(&body) {
+        Ok(xml) => xml,
+        Err(_) => return HttpResponse::BadRequest().body("Corps non UTF-8"),
+    };
+
+    let entries = match parse_feed(xml) {
+        Ok(entries) => entries,
+        Err(e) => {
+            error!("Erreur de parsing du flux Atom WebSub: {}", e);
+            return HttpResponse::BadRequest().body("Flux Atom invalide");
+        }
+    };
+
+    let mut uploads = Vec::new();
+    let mut deleted = Vec::new();
+    for entry in entries {
+        match entry {
+            FeedEntry::Upload(video) => uploads.push(video),
+            FeedEntry::Deleted(video_id) => deleted.push(video_id),
+        }
+    }
+
+    let uploads: Vec<Video> = store.read(|data| {
+        uploads
+            .into_iter()
+            .filter(|video| {
+                let followed = data.websub.contains_key(&video.channel_id);
+                if !followed {
+                    warn!("Vidéo {} reçue pour une chaîne non suivie: {}", video.video_id, video.channel_id);
+                }
+                followed
+            })
+            .collect()
+    });
+
+    if !uploads.is_empty() {
+        let new_count = store.ingest(uploads);
+        info!("Vidéos reçues via WebSub: {} nouvelles", new_count);
+    }
+
+    if !deleted.is_empty() {
+        info!("Vidéos supprimées signalées via WebSub: {:?}", deleted);
+        store.remove_videos(&deleted);
+    }
+
+    HttpResponse::Ok().finish()
+}
+
+pub fn verify_signature(secret: &str, body: &[u8], header: &str) -> bool {
+    let signature = match header.strip_prefix("sha1=").and_then(|s| hex::decode(s).ok()) {
+        Some(signature) => signature,
+        None => return false,
+    };
+
+    let mut mac = HmacSha1::new_from_slice(secret.as_bytes()).expect("HMAC accepte toute taille de clé");
+    mac.update(body);
+    mac.verify_slice(&signature).is_ok()
+}
+
+#[derive(Debug)]
+pub enum FeedEntry {
+    Upload(Video),
+    Deleted(String),
+}
+
+#[derive(Default)]
+struct AtomEntry {
+    video_id: String,
+    channel_id: String,
+    title: String,
+    author: String,
+    published: String,
+}
+
+impl AtomEntry {
+    fn into_video(self) -> Option<Video> {
+        if self.video_id.is_empty() {
+            return None;
+        }
+        let published_at = DateTime::parse_from_rfc3339(&self.published).ok()?;
+
+        Some(Video {
+            url: format!("https://www.youtube.com/watch?v={}", self.video_id),
+            thumbnail: format!("https://i.ytimg.com/vi/{}/mqdefault.jpg", self.video_id),
+            video_id: self.video_id,
+            published_at: published_at.with_timezone(&Utc),
+            title: self.title,
+            channel_title: self.author,
+            channel_id: self.channel_id,
+            description: String::new(),
+            duration_seconds: None,
+        })
+    }
+}
+
+// Analyse le flux Atom poussé par le hub YouTube
+pub fn parse_feed(xml: &str) -> Result<Vec<FeedEntry>, String> {
+    let mut reader = Reader::from_str(xml);
+    reader.trim_text(true);
+
+    let mut entries = Vec::new();
+    let mut entry: Option<AtomEntry> = None;
+    let mut current: Vec<u8> = Vec::new();
+
+    loop {
+        match reader.read_event() {
+            Ok(Event::Start(e)) => {
+                match e.name().as_ref() {
+                    b"entry" => entry = Some(AtomEntry::default()),
+                    b"at:deleted-entry" => {
+                        if let Some(video_id) = deleted_video_id(&e) {
+                            entries.push(FeedEntry::Deleted(video_id));
+                        }
+                    }
+                    _ => {}
+                }
+                current = e.name().as_ref().to_vec();
+            }
+            Ok(Event::Empty(e)) if e.name().as_ref() == b"at:deleted-entry" => {
+                if let Some(video_id) = deleted_video_id(&e) {
+                    entries.push(FeedEntry::Deleted(video_id));
+                }
+            }
+            Ok(Event::Text(t)) => {
+                if let Some(entry) = entry.as_mut() {
+                    let text = t.unescape().map_err(|e| e.to_string())?.into_owned();
+                    match current.as_slice() {
+                        b"yt:videoId" => entry.video_id = text,
+                        b"yt:channelId" => entry.channel_id = text,
+                        b"title" => entry.title = text,
+                        b"name" => entry.author = text,
+                        b"published" => entry.published = text,
+                        _ => {}
+                    }
+                }
+            }
+            Ok(Event::End(e)) => {
+                if e.name().as_ref() == b"entry" {
+                    if let Some(video) = entry.take().and_then(AtomEntry::into_video) {
+                        entries.push(FeedEntry::Upload(video));
+                    }
+                }
+                current.clear();
+            }
+            Ok(Event::Eof) => break,
+            Err(e) => return Err(format!("position {}: {}", reader.buffer_position(), e)),
+            _ => {}
+        }
+    }
+
+    Ok(entries)
+}
+
+fn deleted_video_id(e: &quick_xml::events::BytesStart) -> Option<String> {
+    e.attributes()
+        .flatten()
+        .find(|a| a.key.as_ref() == b"ref")
+        .and_then(|a| a.unescape_value().ok())
+        .and_then(|v| v.strip_prefix("yt:video:").map(|id| id.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SECRET: &str = "secret-partagé";
+
+    fn sign(body: &[u8]) -> String {
+        let mut mac = HmacSha1::new_from_slice(SECRET.as_bytes()).unwrap();
+        mac.update(body);
+        format!("sha1={}", hex::encode(mac.finalize().into_bytes()))
+    }
+
+    #[test]
+    fn accepts_valid_signature() {
+        let body = include_bytes!("../tests/fixtures/websub_feed.xml");
+        assert!(verify_signature(SECRET, body, &sign(body)));
+    }
+
+    #[test]
+    fn rejects_bad_signature() {
+        let body = include_bytes!("../tests/fixtures/websub_feed.xml");
+        assert!(!verify_signature("autre-secret", body, &sign(body)));
+        assert!(!verify_signature(SECRET, b"corps modifie", &sign(body)));
+    }
+
+    #[test]
+    fn rejects_malformed_signature_header() {
+        let body = b"<feed/>";
+        let valid = sign(body);
+        let hex_part = valid.strip_prefix("sha1=").unwrap();
+
+        assert!(!verify_signature(SECRET, body, hex_part));
+        assert!(!verify_signature(SECRET, body, &format!("sha256={}", hex_part)));
+        assert!(!verify_signature(SECRET, body, "sha1=pas-de-l-hexa"));
+        assert!(!verify_signature(SECRET, body, "sha1="));
+        assert!(!verify_signature(SECRET, body, ""));
+    }
+
+    #[test]
+    fn parses_atom_feed_with_deleted_entries() {
+        let entries = parse_feed(include_str!("../tests/fixtures/websub_feed.xml")).unwrap();
+        assert_eq!(entries.len(), 3);
+
+        match &entries[0] {
+            FeedEntry::Upload(video) => {
+                assert_eq!(video.video_id, "dQw4w9WgXcQ");
+                assert_eq!(video.channel_id, "UCaYhcUwRBNscFNUKTjgPFiA");
+                assert_eq!(video.title, "Rust & WebAssembly en pratique");
+                assert_eq!(video.channel_title, "Rust & Co");
+                assert_eq!(video.url, "https://www.youtube.com/watch?v=dQw4w9WgXcQ");
+                assert_eq!(video.published_at.to_rfc3339(), "2026-10-18T09:00:00+00:00");
+            }
+            other => panic!("Upload attendu, obtenu {:?}", other),
+        }
+        // L'entrée sans date de publication est ignorée
+        assert!(matches!(&entries[1], FeedEntry::Deleted(id) if id == "oHg5SJYRHA0"));
+        assert!(matches!(&entries[2], FeedEntry::Deleted(id) if id == "9bZkp7q19f0"));
+    }
+
+    #[test]
+    fn rejects_malformed_feed() {
+        assert!(parse_feed("<feed><entry><title>ouvert</entry></feed>").is_err());
+    }
+
+    #[test]
+    fn confirms_only_requested_intents() {
+        let now = Utc::now();
+        let lease = |requested_hours_ago: i64, expires_at: Option<DateTime<Utc>>, unsubscribing: bool| WebSubLease {
+            topic: topic_url("UCaYhcUwRBNscFNUKTjgPFiA"),
+            requested_at: now - Duration::hours(requested_hours_ago),
+            expires_at,
+            unsubscribe_requested_at: unsubscribing.then_some(now),
+        };
+
+        let pending = lease(0, None, false);
+        assert!(expects_verification(&pending, "subscribe", now));
+        assert!(expects_verification(&pending, "denied", now));
+        assert!(!expects_verification(&pending, "unsubscribe", now));
+
+        // Bail actif sans demande récente: rien n'est attendu
+        let active = lease(48, Some(now + Duration::days(3)), false);
+        assert!(!expects_verification(&active, "subscribe", now));
+        assert!(!expects_verification(&active, "denied", now));
+        assert!(!expects_verification(&active, "unsubscribe", now));
+
+        // Renouvellement en cours
+        let renewing = lease(0, Some(now + Duration::hours(2)), false);
+        assert!(expects_verification(&renewing, "subscribe", now));
+
+        let unsubscribing = lease(48, Some(now + Duration::days(3)), true);
+        assert!(expects_verification(&unsubscribing, "unsubscribe", now));
+        assert!(!expects_verification(&unsubscribing, "subscribe", now));
+    }
+
+    // Hub local qui enregistre les demandes reçues, à la place de pubsubhubbub.appspot.com
+    async fn start_hub() -> (String, web::Data<std::sync::Mutex<Vec<HashMap<String, String>>>>) {
+        use actix_web::{App, HttpServer};
+
+        let requests = web::Data::new(std::sync::Mutex::new(Vec::new()));
+        let received = requests.clone();
+        let server = HttpServer::new(move || {
+            App::new().app_data(received.clone()).route(
+                "/subscribe",
+                web::post().to(
+                    |form: web::Form<HashMap<String, String>>, requests: web::Data<std::sync::Mutex<Vec<HashMap<String, String>>>>| async move {
+                        requests.lock().unwrap().push(form.into_inner());
+                        HttpResponse::Accepted().finish()
+                    },
+                ),
+            )
+        })
+        .workers(1)
+        .bind(("127.0.0.1", 0))
+        .unwrap();
+        let addr = server.addrs()[0];
+        actix_web::rt::spawn(server.run());
+        (format!("http://{}/subscribe", addr), requests)
+    }
+
+    #[actix_web::test]
+    async fn subscribes_verifies_and_receives_through_local_hub() {
+        use actix_web::test;
+
+        let channel_id = "UCaYhcUwRBNscFNUKTjgPFiA";
+        let (hub_url, hub_requests) = start_hub().await;
+        env::set_var("WEBSUB_HUB_URL", &hub_url);
+        env::set_var("WEBSUB_CALLBACK_URL", "http://localhost:8080/websub/callback");
+        env::set_var("WEBSUB_SECRET", SECRET);
+        env::set_var("WEBSUB_LEASE_SECONDS", "3600");
+
+        let store = web::Data::new(Store::in_memory());
+        let app = test::init_service(actix_web::App::new().app_data(store.clone()).service(verify).service(notify)).await;
+
+        // 1. Demande d'abonnement envoyée au hub
+        ensure_subscribed(store.clone(), vec![channel_id.to_string()]).await;
+        let request = hub_requests.lock().unwrap().pop().expect("demande reçue par le hub");
+        assert_eq!(request["hub.mode"], "subscribe");
+        assert_eq!(request["hub.topic"], topic_url(channel_id));
+        assert_eq!(request["hub.callback"], "http://localhost:8080/websub/callback");
+        assert_eq!(request["hub.secret"], SECRET);
+        assert_eq!(request["hub.lease_seconds"], "3600");
+
+        // 2. Vérification par le hub, ici sans hub.lease_seconds
+        let uri = format!("/websub/callback?hub.mode=subscribe&hub.topic={}&hub.challenge=defi-123", request["hub.topic"].replace('?', "%3F").replace('=', "%3D"));
+        let res = test::call_service(&app, test::TestRequest::get().uri(&uri).to_request()).await;
+        assert_eq!(res.status(), 200);
+        assert_eq!(test::read_body(res).await, "defi-123");
+        let expires_at = store.read(|data| data.websub[channel_id].expires_at).expect("bail confirmé");
+        assert!((expires_at - Utc::now() - Duration::seconds(3600)).num_seconds().abs() < 60);
+
+        // 3. Notification signée poussée par le hub
+        let body = include_bytes!("../tests/fixtures/websub_feed.xml");
+        let res = test::call_service(
+            &app,
+            test::TestRequest::post()
+                .uri("/websub/callback")
+                .insert_header(("x-hub-signature", sign(body)))
+                .set_payload(body.to_vec())
+                .to_request(),
+        )
+        .await;
+        assert_eq!(res.status(), 200);
+        assert_eq!(store.read(|data| data.find_video("dQw4w9WgXcQ").map(|v| v.title.clone())).as_deref(), Some("Rust & WebAssembly en pratique"));
+
+        // Une notification mal signée est ignorée
+        let forged = body.iter().copied().map(|b| if b == b'd' { b'e' } else { b }).collect::<Vec<u8>>();
+        store.remove_videos(&["dQw4w9WgXcQ".to_string()]);
+        let res = test::call_service(
+            &app,
+            test::TestRequest::post()
+                .uri("/websub/callback")
+                .insert_header(("x-hub-signature", sign(body)))
+                .set_payload(forged)
+                .to_request(),
+        )
+        .await;
+        assert_eq!(res.status(), 200);
+        assert!(store.read(|data| data.find_video("dQw4w9WgXcQ").is_none()));
+
+        // 4. Plus aucun utilisateur ne suit la chaîne: désabonnement demandé puis vérifié
+        release_unfollowed(store.clone(), vec![channel_id.to_string()]).await;
+        let request = hub_requests.lock().unwrap().pop().expect("désabonnement reçu par le hub");
+        assert_eq!(request["hub.mode"], "unsubscribe");
+        let uri = uri.replace("hub.mode=subscribe", "hub.mode=unsubscribe");
+        let res = test::call_service(&app, test::TestRequest::get().uri(&uri).to_request()).await;
+        assert_eq!(res.status(), 200);
+        assert!(store.read(|data| !data.websub.contains_key(channel_id)));
+    }
+}
@@ -0,0 +1,122 @@
+use actix_web::http::StatusCode;
+use actix_web::HttpResponse;
+use log::error;
+use reqwest::{RequestBuilder, Url};
+use serde_json::Value;
+
+const API_BASE: &str = "https://www.googleapis.com/youtube/v3/";
+
+// Construit l'URL d'un endpoint de l'API YouTube; chaque paramètre est encodé,
+// si bien qu'une saisie contenant '&', '#' ou "key=" reste une simple valeur
+pub fn api_url<I, K, V>(endpoint: &str, params: I) -> Url
+where
+    I: IntoIterator,
+    I::Item: std::borrow::Borrow<(K, V)>,
+    K: AsRef<str>,
+    V: AsRef<str>,
+{
+    let mut url = Url::parse(API_BASE)
+        .and_then(|base| base.join(endpoint))
+        .expect("URL de l'API YouTube invalide");
+    url.query_pairs_mut().extend_pairs(params);
+    url
+}
+
+// Envoie la requête et transmet le statut de l'API au client en cas d'échec
+// (403 pour des commentaires désactivés, 401 pour un token expiré...)
+pub async fn send_json(request: RequestBuilder, label: &str) -> Result<Value, HttpResponse> {
+    let res = match request.send().await {
+        Ok(res) => res,
+        Err(e) => {
+            error!("Erreur reqwest pour {}: {}", label, e.without_url());
+            return Err(HttpResponse::InternalServerError().body("Erreur de communication avec l'API YouTube"));
+        }
+    };
+
+    if !res.status().is_success() {
+        let status = StatusCode::from_u16(res.status().as_u16()).unwrap_or(StatusCode::BAD_GATEWAY);
+        let body: Value = res.json().await.unwrap_or_default();
+        let reason = body["error"]["errors"][0]["reason"].as_str().unwrap_or("inconnue").to_string();
+        error!("Erreur HTTP {} pour {}: {}", status, label, reason);
+        return Err(HttpResponse::build(status).body(format!("Erreur de l'API YouTube: {}", reason)));
+    }
+
+    // Les suppressions répondent 204 sans corps
+    if res.status() == reqwest::StatusCode::NO_CONTENT {
+        return Ok(Value::Null);
+    }
+
+    res.json().await.map_err(|e| {
+        error!("Erreur de parsing de la réponse {}: {}", label, e);
+        HttpResponse::InternalServerError().body("Réponse de l'API YouTube illisible")
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HOSTILE_QUERIES: &[&str] = &[
+        "tom & jerry",
+        "a&key=VOLEE&part=id",
+        "musique#fragment",
+        "100% =? +plus+",
+        "../../channels?mine=true",
+        "café ☕ 東京",
+        "q=1;q=2",
+        "%26%23 déjà encodé",
+        " espaces  multiples ",
+    ];
+
+    fn pairs(url: &Url) -> Vec<(String, String)> {
+        url.query_pairs().map(|(k, v)| (k.into_owned(), v.into_owned())).collect()
+    }
+
+    #[test]
+    fn hostile_queries_round_trip() {
+        for query in HOSTILE_QUERIES {
+            let url = api_url("search", [("part", "snippet"), ("q", *query), ("key", "CLE")]);
+
+            assert_eq!(
+                pairs(&url),
+                vec![
+                    ("part".to_string(), "snippet".to_string()),
+                    ("q".to_string(), query.to_string()),
+                    ("key".to_string(), "CLE".to_string()),
+                ],
+                "requête {:?} mal encodée: {}",
+                query,
+                url
+            );
+            assert_eq!(url.fragment(), None);
+            assert_eq!(url.path(), "/youtube/v3/search");
+        }
+    }
+
+    #[test]
+    fn injected_key_does_not_override_real_key() {
+        let url = api_url("search", [("q", "x&key=VOLEE"), ("key", "CLE")]);
+        let keys: Vec<String> = pairs(&url).into_iter().filter(|(k, _)| k == "key").map(|(_, v)| v).collect();
+        assert_eq!(keys, vec!["CLE".to_string()]);
+    }
+
+    #[test]
+    fn reqwest_request_keeps_encoded_url() {
+        let query = "a&b#c";
+        let request = reqwest::Client::new()
+            .get(api_url("search", [("q", query)]))
+            .build()
+            .unwrap();
+        assert_eq!(pairs(request.url()), vec![("q".to_string(), query.to_string())]);
+    }
+
+    #[test]
+    fn joined_ids_and_page_tokens_are_preserved() {
+        let ids = ["UCabc", "UCdef"].join(",");
+        let url = api_url("channels", vec![("id", ids.as_str()), ("pageToken", "CAUQAA")]);
+        assert_eq!(
+            pairs(&url),
+            vec![("id".to_string(), "UCabc,UCdef".to_string()), ("pageToken".to_string(), "CAUQAA".to_string())]
+        );
+    }
+}
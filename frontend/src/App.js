@@ -25,6 +25,7 @@ function MainApp() {
 	const [loading, setLoading] = useState(false);
 	const [error, setError] = useState(null);
 	const [searchInput, setSearchInput] = useState('');
+	const [nextPageToken, setNextPageToken] = useState(null);
 
 	useEffect(() => {
 		console.log('App mounted, checking authentication');
@@ -139,6 +140,7 @@ function MainApp() {
 
 				if (Array.isArray(response.data)) {
 					setVideos(response.data);
+					setNextPageToken(null);
 					setError(null);
 				} else if (response.data.message) {
 					setError(response.data.message);
@@ -172,7 +174,7 @@ function MainApp() {
 	};
 
 	// Fonction de recherche
-	const handleSearch = async () => {
+	const handleSearch = async (pageToken = null) => {
 		if (!searchInput.trim()) {
 			setError('Veuillez entrer un terme de recherche');
 			return;
@@ -182,11 +184,13 @@ function MainApp() {
 		setError(null);
 
 		try {
-			const response = await axios.get(`http://localhost:8080/search/${encodeURIComponent(searchInput)}`);
+			const params = pageToken ? {page_token: pageToken} : {};
+			const response = await axios.get(`http://localhost:8080/search/${encodeURIComponent(searchInput)}`, {params});
 			console.log('Résultats de recherche:', response.data);
 
-			if (Array.isArray(response.data)) {
-				setVideos(response.data);
+			if (Array.isArray(response.data.items)) {
+				setVideos(pageToken ? [...videos, ...response.data.items] : response.data.items);
+				setNextPageToken(response.data.next_page_token || null);
 				setError(null);
 			} else if (response.data.message) {
 				setError(response.data.message);
@@ -217,6 +221,7 @@ function MainApp() {
 		localStorage.removeItem('expires_in');
 		setIsAuthenticated(false);
 		setVideos([]);
+		setNextPageToken(null);
 		setError(null);
 	};
 
@@ -284,7 +289,7 @@ function MainApp() {
 					<div className="header-center">
 						<div className="search-container">
 							<input type="text" className="search-input" placeholder="Rechercher" value={searchInput} onChange={(e) => setSearchInput(e.target.value)} onKeyPress={handleKeyPress}/>
-							<button className="search-button" onClick={handleSearch} disabled={loading}>
+							<button className="search-button" onClick={() => handleSearch()} disabled={loading}>
 								<svg viewBox="0 0 24 24" width="24" height="24">
 									<path fill="#fff"
 												d="M15.5 14h-.79l-.28-.27C15.41 12.59 16 11.11 16 9.5 16 5.91 13.09 3 9.5 3S3 5.91 3 9.5 5.91 16 9.5 16c1.61 0 3.09-.59 4.23-1.57l.27.28v.79l5 4.99L20.49 19l-4.99-5zm-6 0C7.01 14 5 11.99 5 9.5S7.01 5 9.5 5 14 7.01 14 9.5 11.99 14 9.5 14z"/>
@@ -420,6 +425,13 @@ function MainApp() {
 							</p>
 						)}
 						{videos && videos.length > 0 && <VideoGrid videos={videos}/>}
+						{nextPageToken && !loading && (
+							<div style={{textAlign: 'center', padding: '1rem'}}>
+								<button onClick={() => handleSearch(nextPageToken)}>
+									Plus de résultats
+								</button>
+							</div>
+						)}
 					</div>
 				</div>
 			</div>